@@ -0,0 +1,230 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::decoder::ReportFormat;
+use crate::transport::{Transport, TransportError};
+
+// File layout (little-endian):
+//   header: b"X1CAP" | version u8 | format u8 (0 = HID, 1 = libusb) | serial_len u8 | serial
+//   record: timestamp_us u64 (since capture start) | len u16 | report bytes
+const MAGIC: &[u8; 5] = b"X1CAP";
+const VERSION: u8 = 1;
+pub const CAPTURE_EXTENSION: &str = "x1cap";
+
+pub struct CaptureRecord {
+    pub at: Duration,
+    pub report: Vec<u8>,
+}
+
+pub struct Capture {
+    pub serial: String,
+    pub format: ReportFormat,
+    pub records: Vec<CaptureRecord>,
+}
+
+impl Capture {
+    pub fn load(path: &Path) -> io::Result<Capture> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut magic = [0u8; 5];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not an X1 capture file"));
+        }
+        let mut head = [0u8; 3];
+        reader.read_exact(&mut head)?;
+        if head[0] != VERSION {
+            return Err(invalid_data(&format!("unsupported capture version {}", head[0])));
+        }
+        let format = match head[1] {
+            0 => ReportFormat::Hid,
+            1 => ReportFormat::Usb,
+            other => return Err(invalid_data(&format!("unknown report format {}", other))),
+        };
+        let mut serial = vec![0u8; head[2] as usize];
+        reader.read_exact(&mut serial)?;
+        let serial = String::from_utf8_lossy(&serial).into_owned();
+
+        let mut records = Vec::new();
+        loop {
+            let mut ts = [0u8; 8];
+            match reader.read_exact(&mut ts) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e),
+            }
+            let mut len = [0u8; 2];
+            reader.read_exact(&mut len)?;
+            let mut report = vec![0u8; u16::from_le_bytes(len) as usize];
+            reader.read_exact(&mut report)?;
+            records.push(CaptureRecord {
+                at: Duration::from_micros(u64::from_le_bytes(ts)),
+                report,
+            });
+        }
+        Ok(Capture { serial, format, records })
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+pub struct CaptureWriter {
+    out: BufWriter<File>,
+    start: Instant,
+}
+
+impl CaptureWriter {
+    pub fn create(path: &Path, serial: &str, format: ReportFormat) -> io::Result<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+        let serial = &serial.as_bytes()[..serial.len().min(u8::MAX as usize)];
+        out.write_all(MAGIC)?;
+        out.write_all(&[VERSION, format_byte(format), serial.len() as u8])?;
+        out.write_all(serial)?;
+        out.flush()?;
+        Ok(Self { out, start: Instant::now() })
+    }
+
+    pub fn write_report(&mut self, report: &[u8]) -> io::Result<()> {
+        let at = self.start.elapsed();
+        self.write_report_at(at, report)
+    }
+
+    pub fn write_report_at(&mut self, at: Duration, report: &[u8]) -> io::Result<()> {
+        let report = &report[..report.len().min(u16::MAX as usize)];
+        self.out.write_all(&(at.as_micros() as u64).to_le_bytes())?;
+        self.out.write_all(&(report.len() as u16).to_le_bytes())?;
+        self.out.write_all(report)?;
+        // Flush per report so a crash still leaves a usable capture.
+        self.out.flush()
+    }
+}
+
+fn format_byte(format: ReportFormat) -> u8 {
+    match format {
+        ReportFormat::Hid => 0,
+        ReportFormat::Usb => 1,
+    }
+}
+
+/// Transport wrapper that writes every report read from the device to a capture file.
+pub struct CaptureTransport {
+    inner: Box<dyn Transport>,
+    writer: CaptureWriter,
+}
+
+impl CaptureTransport {
+    pub fn new(inner: Box<dyn Transport>, writer: CaptureWriter) -> Self {
+        Self { inner, writer }
+    }
+}
+
+impl Transport for CaptureTransport {
+    fn read_report(&mut self, buf: &mut [u8], timeout_ms: i32) -> Result<usize, TransportError> {
+        let n = self.inner.read_report(buf, timeout_ms)?;
+        if let Err(e) = self.writer.write_report(&buf[..n]) {
+            eprintln!("[CAPTURE] write failed: {}", e);
+        }
+        Ok(n)
+    }
+
+    fn write_report(&mut self, data: &[u8]) -> Result<usize, TransportError> {
+        self.inner.write_report(data)
+    }
+
+    fn send_feature_report(&mut self, data: &[u8]) -> Result<(), TransportError> {
+        self.inner.send_feature_report(data)
+    }
+
+    fn configure(&mut self) -> Result<(), TransportError> {
+        self.inner.configure()
+    }
+
+    fn flush(&mut self) {
+        self.inner.flush()
+    }
+}
+
+/// Wrap `transport` in a [`CaptureTransport`] when a capture directory is configured.
+/// Each device gets its own file, named after its serial and the start time.
+pub fn with_capture(
+    transport: Box<dyn Transport>,
+    serial: &str,
+    format: ReportFormat,
+    capture_dir: Option<&Path>,
+) -> Box<dyn Transport> {
+    let dir = match capture_dir {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => return transport,
+    };
+    let started = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let file_serial: String = serial
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let path = dir.join(format!("x1-{}-{}.{}", file_serial, started, CAPTURE_EXTENSION));
+    match std::fs::create_dir_all(dir).and_then(|_| CaptureWriter::create(&path, serial, format)) {
+        Ok(writer) => {
+            println!("Capturing reports to {}", path.display());
+            Box::new(CaptureTransport::new(transport, writer))
+        }
+        Err(e) => {
+            eprintln!("[CAPTURE] could not create {}: {}", path.display(), e);
+            transport
+        }
+    }
+}
+
+/// Transport that plays a capture back with its original timing, as if the device were attached.
+/// Output reports are accepted and dropped; once the capture ends, reads fail with `Disconnected`.
+pub struct ReplayTransport {
+    records: VecDeque<CaptureRecord>,
+    start: Option<Instant>,
+}
+
+impl ReplayTransport {
+    pub fn new(capture: Capture) -> Self {
+        Self {
+            records: capture.records.into(),
+            start: None,
+        }
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn read_report(&mut self, buf: &mut [u8], timeout_ms: i32) -> Result<usize, TransportError> {
+        let start = *self.start.get_or_insert_with(Instant::now);
+        let due = match self.records.front() {
+            Some(record) => start + record.at,
+            None => return Err(TransportError::Disconnected),
+        };
+        let now = Instant::now();
+        if due > now {
+            let wait = due - now;
+            let timeout = Duration::from_millis(timeout_ms.max(0) as u64);
+            if wait > timeout {
+                std::thread::sleep(timeout);
+                return Err(TransportError::Timeout);
+            }
+            std::thread::sleep(wait);
+        }
+        let record = self.records.pop_front().ok_or(TransportError::Disconnected)?;
+        let len = record.report.len().min(buf.len());
+        buf[..len].copy_from_slice(&record.report[..len]);
+        Ok(len)
+    }
+
+    fn write_report(&mut self, data: &[u8]) -> Result<usize, TransportError> {
+        Ok(data.len())
+    }
+
+    fn send_feature_report(&mut self, _data: &[u8]) -> Result<(), TransportError> {
+        Ok(())
+    }
+}
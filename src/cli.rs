@@ -0,0 +1,261 @@
+use std::path::PathBuf;
+
+use clap::builder::FalseyValueParser;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use crate::devices::PortSuffix;
+use crate::settings::{Backend, LedTestSettings, Settings};
+
+#[derive(Parser, Debug)]
+#[command(name = "easy_kontrol_x1", version, about = "Easy KONTROL X1 Driver", args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Options for `run`, which is what happens without a subcommand
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Run the driver (default when no subcommand is given)
+    Run(RunArgs),
+    /// List connected X1 MK1/MK2 devices
+    List(ListArgs),
+    /// Print decoded control events from a connected X1
+    Monitor(MonitorArgs),
+    /// Drive the LEDs for diagnostics
+    LedTest(LedTestArgs),
+    /// Show or choose mapping profiles; a running driver switches within a second
+    #[command(subcommand)]
+    Profile(ProfileCommand),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ProfileCommand {
+    /// List profiles and which controllers use them
+    List,
+    /// Use a profile for every controller, or only for --serial
+    Set {
+        /// Profile name (`default` is the board.yml mapping)
+        name: String,
+        /// Serial, `list` id or alias of the controller to switch
+        #[arg(long)]
+        serial: Option<String>,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum BackendArg {
+    Hid,
+    Libusb,
+}
+
+impl From<BackendArg> for Backend {
+    fn from(b: BackendArg) -> Self {
+        match b {
+            BackendArg::Hid => Backend::Hid,
+            BackendArg::Libusb => Backend::Libusb,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum PortSuffixArg {
+    Index,
+    Serial,
+}
+
+impl From<PortSuffixArg> for PortSuffix {
+    fn from(s: PortSuffixArg) -> Self {
+        match s {
+            PortSuffixArg::Index => PortSuffix::Index,
+            PortSuffixArg::Serial => PortSuffix::Serial,
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct DeviceArgs {
+    /// Board mapping file (default: bundled board.yml with the user's board.yml merged on top)
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+    /// Device access backend; `hid` falls back to libusb when no HID device is found
+    #[arg(long, value_enum, default_value = "hid")]
+    pub backend: BackendArg,
+    /// Log LED writes and USB endpoint details
+    #[arg(long, env = "LED_DEBUG", value_parser = FalseyValueParser::new())]
+    pub led_debug: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct RunArgs {
+    #[command(flatten)]
+    pub device: DeviceArgs,
+    /// Print every changed report byte (layout discovery)
+    #[arg(long, env = "LOG_BYTES", value_parser = FalseyValueParser::new())]
+    pub log_bytes: bool,
+    /// libusb: keep driving LEDs when the input endpoint cannot be configured
+    #[arg(long)]
+    pub led_only: bool,
+    /// Write raw reports of every device to capture files in DIR
+    #[arg(long, value_name = "DIR", env = "X1_CAPTURE")]
+    pub capture: Option<PathBuf>,
+    /// Replay a capture file instead of opening devices
+    #[arg(long, value_name = "FILE", env = "X1_REPLAY", conflicts_with = "capture")]
+    pub replay: Option<PathBuf>,
+    /// Menu bar icon (macOS)
+    #[arg(long, value_name = "FILE", env = "X1_MENU_ICON")]
+    pub menu_icon: Option<PathBuf>,
+    /// Port names for controllers without a `port_name` in the config: numbered by connection
+    /// order, or suffixed with the serial
+    #[arg(long, value_enum, default_value = "index")]
+    pub port_suffix: PortSuffixArg,
+    /// Share one MIDI port between all controllers: the first uses channels 1-3, the second 4-6, ...
+    #[arg(long)]
+    pub merged_port: bool,
+    /// Start every controller with this profile (remembered for the next start)
+    #[arg(long, value_name = "NAME", env = "X1_PROFILE")]
+    pub profile: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct ListArgs {
+    /// Board mapping file whose `devices:` section names the ports
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+    /// Default port naming, as for `run`
+    #[arg(long, value_enum, default_value = "index")]
+    pub port_suffix: PortSuffixArg,
+}
+
+#[derive(Args, Debug)]
+pub struct MonitorArgs {
+    #[command(flatten)]
+    pub device: DeviceArgs,
+    /// Serial, `list` id or alias of the device to monitor (default: first found)
+    #[arg(long)]
+    pub serial: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct LedTestArgs {
+    #[command(flatten)]
+    pub device: DeviceArgs,
+    /// Walk a single lit LED across the frame
+    #[arg(long)]
+    pub chase: bool,
+    /// Turn every LED off and exit
+    #[arg(long, conflicts_with_all = ["all", "idx", "chase"])]
+    pub clear: bool,
+    /// Light every LED
+    #[arg(long)]
+    pub all: bool,
+    /// Also write the extended LED range (report 0x81)
+    #[arg(long)]
+    pub ext: bool,
+    /// Light only this LED index
+    #[arg(long)]
+    pub idx: Option<usize>,
+    /// LED bank for --idx: 80, 81 or any other value for the 32-byte frame (hex)
+    #[arg(long, value_parser = parse_hex_u8, default_value = "80")]
+    pub bank: u8,
+    /// Only write this output report ID (hex); "all" writes every known layout
+    #[arg(long, default_value = "80")]
+    pub rid: String,
+    /// Exit after writing the --idx frame once
+    #[arg(long, requires = "idx")]
+    pub once: bool,
+}
+
+fn parse_hex_u8(s: &str) -> Result<u8, String> {
+    u8::from_str_radix(s.trim_start_matches("0x"), 16).map_err(|e| format!("invalid hex byte {:?}: {}", s, e))
+}
+
+impl DeviceArgs {
+    fn apply(&self, settings: &mut Settings) {
+        settings.config_path = self.config.clone();
+        settings.backend = self.backend.into();
+        settings.led_debug = self.led_debug;
+    }
+}
+
+impl RunArgs {
+    pub fn settings(&self) -> Settings {
+        let mut settings = Settings::default();
+        self.device.apply(&mut settings);
+        settings.log_bytes = self.log_bytes;
+        settings.led_only = self.led_only;
+        settings.capture_dir = self.capture.clone();
+        settings.replay = self.replay.clone();
+        settings.menu_icon = self.menu_icon.clone();
+        settings.port_suffix = self.port_suffix.into();
+        settings.merged_port = self.merged_port;
+        settings.profile = self.profile.clone();
+        settings
+    }
+}
+
+impl ListArgs {
+    pub fn settings(&self) -> Settings {
+        Settings {
+            config_path: self.config.clone(),
+            port_suffix: self.port_suffix.into(),
+            ..Settings::default()
+        }
+    }
+}
+
+impl MonitorArgs {
+    pub fn settings(&self) -> Settings {
+        let mut settings = Settings::default();
+        self.device.apply(&mut settings);
+        settings
+    }
+}
+
+impl LedTestArgs {
+    pub fn settings(&self) -> Result<Settings, String> {
+        let mut settings = Settings::default();
+        self.device.apply(&mut settings);
+        let rid = if self.rid == "all" { None } else { Some(parse_hex_u8(&self.rid)?) };
+        settings.led_test = LedTestSettings {
+            chase: self.chase,
+            clear: self.clear,
+            all: self.all,
+            ext: self.ext,
+            idx: self.idx,
+            bank: self.bank,
+            rid,
+            once: self.once,
+        };
+        Ok(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsStr;
+
+    use clap::builder::TypedValueParser;
+    use clap::CommandFactory;
+
+    use super::*;
+
+    /// LED_DEBUG and LOG_BYTES take the usual shell spellings; checked on the parser itself, since
+    /// setting the variables would race with tests on other threads.
+    #[test]
+    fn debug_env_vars_accept_1() {
+        let cmd = Cli::command();
+        for (id, env) in [("led_debug", "LED_DEBUG"), ("log_bytes", "LOG_BYTES")] {
+            let arg = cmd.get_arguments().find(|a| a.get_id() == id).unwrap();
+            assert_eq!(arg.get_env(), Some(OsStr::new(env)));
+        }
+        let parse = |value: &str| FalseyValueParser::new().parse_ref(&cmd, None, OsStr::new(value)).unwrap();
+        assert!(parse("1"));
+        assert!(parse("true"));
+        assert!(!parse("0"));
+        assert!(!parse("false"));
+        assert!(!parse(""));
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -20,9 +22,223 @@ pub struct YamlButton {
     pub write_idx: Option<u8>,
     pub midi_ctrl_ch: u8,
     pub hotcue_ignore: Option<bool>,
+    /// Message type sent (default `CC`); `midi_ctrl_ch` is its CC, note, program or parameter number.
+    pub message: Option<YamlMessageType>,
+    /// MIDI channel, 1-16 (default 1).
+    pub channel: Option<u8>,
+    /// NRPN parameter MSB; `midi_ctrl_ch` is the LSB.
+    pub nrpn_msb: Option<u8>,
+    /// Knobs: send 14-bit values from the full 12-bit reading (`CC` as MSB/LSB pair on
+    /// `midi_ctrl_ch` and `midi_ctrl_ch` + 32, `PitchBend` or `NRPN`).
+    pub high_res: Option<bool>,
+    /// Knobs: response curve (default `Linear`).
+    pub curve: Option<YamlCurve>,
+    /// `CenterDetent`: width of the flat zone around the middle, as a fraction of the travel (default 0.1).
+    pub detent: Option<f32>,
+    /// `Table`: output values (0-127) at evenly spaced knob positions, interpolated in between.
+    pub curve_table: Option<Vec<u8>>,
+    /// Knobs and `Absolute` encoders: lowest and highest value sent, 0-127 (scaled up for 14-bit output).
+    pub min: Option<u8>,
+    pub max: Option<u8>,
+    /// Knobs: reverse the direction of travel.
+    pub invert: Option<bool>,
+    /// Knobs: soft takeover; after a layer or profile switch, or a change from software, nothing is
+    /// sent until the knob reaches the value last sent or received for its CC.
+    pub pickup: Option<bool>,
+    /// Knobs with `pickup`: LED lit while the knob has not picked up yet.
+    pub pickup_led: Option<u8>,
+    /// Knobs with `pickup`: where no value is known yet, wait for the software to echo one instead
+    /// of sending right away.
+    pub pickup_wait: Option<bool>,
+    /// Knobs: raw 12-bit steps a reading has to move before it counts, against flicker of worn pots.
+    pub deadband: Option<u16>,
+    /// Knobs: smooth raw readings before the deadband.
+    pub smoothing: Option<YamlSmoothing>,
+    /// Readings `smoothing` averages over, 1-32 (default 4).
+    pub smoothing_window: Option<u8>,
+    /// Encoders: how detents are sent (default `TwosComplement`).
+    pub encoding: Option<YamlEncoding>,
+    /// `Notes`: note for counter-clockwise detents (default `midi_ctrl_ch` + 1).
+    pub note_down: Option<u8>,
+    /// Encoders: most steps one detent moves when turned fast, 1-16 (default 1, no acceleration).
+    pub acceleration: Option<u8>,
+    /// Send 127 on press and 0 on release, instead of both on release.
+    pub momentary: Option<bool>,
+    /// The LED is the blue part of an RGB pad (red and green are the two before it); incoming CC
+    /// values pick a color.
+    pub rgb_led: Option<bool>,
+    /// Buttons: long press, double tap and auto-repeat, instead of a plain press and release.
+    pub gestures: Option<YamlGestures>,
+    /// Output while a modifier layer is active, by layer name; overrides the layer's own `channel`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub layers: BTreeMap<String, YamlLayerOutput>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum YamlMessageType {
+    #[default]
+    #[serde(rename = "CC")]
+    Cc,
+    /// Note On with the value as velocity; Note Off for 0.
+    Note,
+    /// Buttons send program `midi_ctrl_ch` on press; knobs and encoders send their value as the program.
+    ProgramChange,
+    /// The value scaled to 14 bits; the channel is the only address.
+    PitchBend,
+    #[serde(rename = "NRPN")]
+    Nrpn,
+}
+
+/// How a knob's position maps to its output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum YamlCurve {
+    #[default]
+    Linear,
+    /// Rises fast at the start, fine control at the top.
+    Log,
+    /// Fine control at the start, rises fast at the top.
+    Exp,
+    /// Fine control at both ends.
+    SCurve,
+    /// Linear with a flat zone of `detent` around the middle that always sends the center value.
+    CenterDetent,
+    /// `curve_table`.
+    Table,
+}
+
+/// Smoothing of a knob's raw readings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum YamlSmoothing {
+    /// Mean of the last `smoothing_window` readings.
+    Average,
+    /// Exponential moving average, as responsive as a `smoothing_window` average.
+    Ema,
+}
+
+/// Values an encoder sends per turn; relative ones carry the number of steps `n`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum YamlEncoding {
+    /// `n` clockwise, 128 - `n` counter-clockwise.
+    #[default]
+    TwosComplement,
+    /// 64 + `n` clockwise, 64 - `n` counter-clockwise.
+    BinaryOffset,
+    /// `n` clockwise, 64 + `n` counter-clockwise.
+    SignMagnitude,
+    /// A note per step: `midi_ctrl_ch` clockwise, `note_down` counter-clockwise.
+    Notes,
+    /// A value between `min` and `max` that the encoder turns up and down.
+    Absolute,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum YamlModifierMode {
+    /// Layer is on while the button is held.
+    Momentary,
+    /// Each press (on release) turns the layer on or off.
+    Latching,
+}
+
+/// A button that switches a layer of alternative outputs.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct YamlModifier {
+    pub layer: String,
+    pub button: String,
+    pub mode: YamlModifierMode,
+    /// Channel (1-16) every control sends on while the layer is on, unless its `layers` entry says
+    /// otherwise. Without it controls are unchanged by the layer.
+    pub channel: Option<u8>,
+    /// While the layer is on, holding a button past the long-press time sends its CC + 32.
+    #[serde(default)]
+    pub long_press: bool,
+}
+
+/// What a control sends while a layer is active: `unchanged`, or a channel (1-16) and/or CC.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum YamlLayerOutput {
+    Keyword(YamlLayerKeyword),
+    Output(YamlOutput),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum YamlLayerKeyword {
+    #[serde(rename = "unchanged")]
+    Unchanged,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct YamlOutput {
+    pub channel: Option<u8>,
+    /// Replaces `midi_ctrl_ch`, whatever the message type.
+    pub cc: Option<u8>,
+}
+
+/// Press gestures of a button. Times are in milliseconds; outputs replace the button's channel
+/// and/or CC.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct YamlGestures {
+    /// Sent instead of the tap when held for `long_press_ms` (default: CC + 32).
+    pub long_press: Option<YamlOutput>,
+    /// Hold time of a long press (default 800); on its own it makes holds send CC + 32.
+    pub long_press_ms: Option<u64>,
+    /// Sent instead of two taps that come within `double_tap_ms` (default 300); a single tap
+    /// waits that long before it is sent.
+    pub double_tap: Option<YamlOutput>,
+    pub double_tap_ms: Option<u64>,
+    /// Tap on press, then again every `repeat_ms` while held, starting after `repeat_delay_ms`
+    /// (default 400).
+    pub repeat_ms: Option<u64>,
+    pub repeat_delay_ms: Option<u64>,
+}
+
+impl YamlLayerOutput {
+    /// The channel and CC to replace; both `None` for `unchanged`.
+    pub fn output(&self) -> YamlOutput {
+        match self {
+            YamlLayerOutput::Keyword(YamlLayerKeyword::Unchanged) => YamlOutput::default(),
+            YamlLayerOutput::Output(output) => *output,
+        }
+    }
+}
+
+/// Per-controller settings, matched by serial (or the id shown by `list`).
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct YamlDevice {
+    pub serial: String,
+    /// Short name accepted wherever a serial is asked for on the command line.
+    pub alias: Option<String>,
+    /// Virtual MIDI port name for this controller.
+    pub port_name: Option<String>,
+    /// Profile used unless one was chosen for this controller with `profile set --serial` or on the device.
+    pub profile: Option<String>,
+    /// LED brightness, 0-127 (default 127).
+    pub led_brightness: Option<u8>,
+    /// Added to every MIDI channel the controller sends on, and taken off what it receives.
+    pub channel_offset: Option<u8>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct YamlConfig {
     pub buttons: Vec<YamlButton>,
+    #[serde(default)]
+    pub devices: Vec<YamlDevice>,
+    /// Buttons that, pressed together, switch the controller to the next profile.
+    #[serde(default)]
+    pub profile_switch: Vec<String>,
+    /// Modifier buttons, in order of precedence: the first active layer that changes a control's
+    /// output decides it.
+    #[serde(default)]
+    pub modifiers: Vec<YamlModifier>,
+}
+
+impl YamlConfig {
+    /// The `devices:` entry whose serial or alias is `id`.
+    pub fn device(&self, id: &str) -> Option<&YamlDevice> {
+        self.devices
+            .iter()
+            .find(|d| d.serial == id || d.alias.as_deref() == Some(id))
+    }
 }
@@ -0,0 +1,184 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde_yaml::Value;
+
+use crate::conf::YamlConfig;
+use crate::utils::get_yaml_path;
+use crate::validate::{self, ConfigError};
+
+/// Directory name inside the platform config dir (`~/Library/Application Support` on macOS,
+/// `$XDG_CONFIG_HOME` or `~/.config` on Linux).
+pub const CONFIG_DIR_NAME: &str = "easy-kontrol-x1";
+/// User override file inside [`user_config_dir`].
+pub const USER_CONFIG_FILE: &str = "board.yml";
+
+/// Per-user config directory; survives app updates, unlike the bundle's Resources.
+pub fn user_config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(CONFIG_DIR_NAME))
+}
+
+/// Where the board mapping comes from, in lookup order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConfigFiles {
+    /// `--config`: used as is, nothing is merged.
+    Explicit(PathBuf),
+    /// The shipped board.yml with the user's board.yml, if any, merged on top.
+    Layered { bundled: PathBuf, user: Option<PathBuf> },
+}
+
+impl ConfigFiles {
+    /// `explicit` if given, else the bundled board.yml plus the user file when it exists.
+    pub fn resolve(explicit: Option<&Path>) -> Result<Self, String> {
+        if let Some(path) = explicit {
+            return Ok(ConfigFiles::Explicit(path.to_path_buf()));
+        }
+        let bundled = get_yaml_path().ok_or("board.yml not found; pass --config <path>")?;
+        let user = user_config_dir()
+            .map(|dir| dir.join(USER_CONFIG_FILE))
+            .filter(|path| path.exists());
+        Ok(ConfigFiles::Layered { bundled, user })
+    }
+
+    /// Every file the mapping is read from.
+    pub fn paths(&self) -> Vec<&Path> {
+        match self {
+            ConfigFiles::Explicit(path) => vec![path],
+            ConfigFiles::Layered { bundled, user } => {
+                let mut paths = vec![bundled.as_path()];
+                paths.extend(user.as_deref());
+                paths
+            }
+        }
+    }
+
+    pub fn load(&self) -> Result<YamlConfig, ConfigError> {
+        match self {
+            ConfigFiles::Explicit(path) | ConfigFiles::Layered { bundled: path, user: None } => {
+                validate::load_config_file(path)
+            }
+            ConfigFiles::Layered { bundled, user: Some(user) } => load_layered(bundled, user),
+        }
+    }
+}
+
+impl fmt::Display for ConfigFiles {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigFiles::Explicit(path) | ConfigFiles::Layered { bundled: path, user: None } => {
+                write!(f, "{}", path.display())
+            }
+            ConfigFiles::Layered { bundled, user: Some(user) } => {
+                write!(f, "{} + {}", bundled.display(), user.display())
+            }
+        }
+    }
+}
+
+/// Merge the user file over the bundled one and validate the result. Problems are reported
+/// against the user file, since the bundled mapping is known to be valid on its own.
+fn load_layered(bundled: &Path, user: &Path) -> Result<YamlConfig, ConfigError> {
+    let read = |path: &Path| -> Result<(String, Value), ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(|e| ConfigError::single(path, e.to_string()))?;
+        let value = serde_yaml::from_str(&text).map_err(|e| ConfigError::single(path, e.to_string()))?;
+        Ok((text, value))
+    };
+    let (_, mut merged) = read(bundled)?;
+    let (user_text, overlay) = read(user)?;
+    merge_config(&mut merged, overlay).map_err(|e| ConfigError::single(user, e))?;
+    let config: YamlConfig = serde_yaml::from_value(merged).map_err(|e| ConfigError::single(user, e.to_string()))?;
+    validate::check(user, config, &user_text)
+}
+
+/// Apply `overlay` to `base`. Entries of `buttons` are matched by `name`, `devices` by `serial` and
+/// `modifiers` by `layer`:
+/// a matching entry has only the keys given in the overlay replaced, anything else is appended.
+/// Other top-level keys replace the base value.
+pub fn merge_config(base: &mut Value, overlay: Value) -> Result<(), String> {
+    let Value::Mapping(overlay) = overlay else {
+        return match overlay {
+            Value::Null => Ok(()),
+            _ => Err("user config must be a mapping".to_string()),
+        };
+    };
+    let Value::Mapping(base) = base else {
+        return Err("bundled config must be a mapping".to_string());
+    };
+    for (key, value) in overlay {
+        let merge_key = match key.as_str() {
+            Some("buttons") => Some("name"),
+            Some("devices") => Some("serial"),
+            Some("modifiers") => Some("layer"),
+            _ => None,
+        };
+        match (merge_key, base.get_mut(&key)) {
+            (Some(merge_key), Some(Value::Sequence(entries))) => {
+                let Value::Sequence(overrides) = value else {
+                    return Err(format!("{} must be a list", key.as_str().unwrap_or_default()));
+                };
+                for entry in overrides {
+                    merge_entry(entries, entry, merge_key)?;
+                }
+            }
+            _ => {
+                base.insert(key, value);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn merge_entry(entries: &mut Vec<Value>, entry: Value, merge_key: &str) -> Result<(), String> {
+    let Value::Mapping(fields) = entry else {
+        return Err(format!("list entries must be mappings with a `{}`", merge_key));
+    };
+    let id = fields
+        .get(merge_key)
+        .cloned()
+        .ok_or_else(|| format!("list entry without `{}`", merge_key))?;
+    let existing = entries
+        .iter_mut()
+        .filter_map(Value::as_mapping_mut)
+        .find(|existing| existing.get(merge_key) == Some(&id));
+    match existing {
+        Some(existing) => existing.extend(fields),
+        None => entries.push(Value::Mapping(fields)),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_entries_override_by_name_and_append() {
+        let mut base: Value = serde_yaml::from_str(
+            r#"
+buttons:
+  - { name: "A", type: "Hold", read_i: 10, read_j: 1, midi_ctrl_ch: 1 }
+  - { name: "B", type: "Hold", read_i: 10, read_j: 2, midi_ctrl_ch: 2 }
+"#,
+        )
+        .unwrap();
+        let overlay: Value = serde_yaml::from_str(
+            r#"
+buttons:
+  - { name: "B", midi_ctrl_ch: 20 }
+  - { name: "C", type: "Toggle", read_i: 11, read_j: 0, midi_ctrl_ch: 3 }
+devices:
+  - { serial: "X", port_name: "Left" }
+"#,
+        )
+        .unwrap();
+        merge_config(&mut base, overlay).unwrap();
+        let config: YamlConfig = serde_yaml::from_value(base).unwrap();
+        let summary: Vec<(&str, u8, u8)> = config
+            .buttons
+            .iter()
+            .map(|b| (b.name.as_str(), b.read_j.unwrap(), b.midi_ctrl_ch))
+            .collect();
+        assert_eq!(summary, vec![("A", 1, 1), ("B", 2, 20), ("C", 0, 3)]);
+        assert_eq!(config.devices[0].port_name.as_deref(), Some("Left"));
+    }
+}
@@ -0,0 +1,102 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::conf::YamlConfig;
+use crate::config_files::ConfigFiles;
+use crate::validate::ConfigError;
+
+/// How often the config files' modification times are checked.
+pub const CONFIG_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The mapping every handler runs with. Each replacement bumps the generation, which handlers
+/// compare with the one they were built from to notice a reload.
+pub struct SharedConfig {
+    current: Mutex<(u64, Arc<YamlConfig>)>,
+}
+
+impl SharedConfig {
+    pub fn new(config: YamlConfig) -> Self {
+        Self {
+            current: Mutex::new((0, Arc::new(config))),
+        }
+    }
+
+    pub fn current(&self) -> (u64, Arc<YamlConfig>) {
+        self.current.lock().unwrap().clone()
+    }
+
+    /// The current mapping if it was replaced after `generation`.
+    pub fn newer_than(&self, generation: u64) -> Option<(u64, Arc<YamlConfig>)> {
+        let current = self.current.lock().unwrap();
+        if current.0 != generation {
+            Some(current.clone())
+        } else {
+            None
+        }
+    }
+
+    fn replace(&self, config: YamlConfig) {
+        let mut current = self.current.lock().unwrap();
+        *current = (current.0 + 1, Arc::new(config));
+    }
+}
+
+/// Path and modification time of each config file.
+type Stamps = Vec<(PathBuf, Option<SystemTime>)>;
+
+/// Which files the mapping comes from and when they were last modified.
+fn stamps(explicit: Option<&PathBuf>) -> Option<(ConfigFiles, Stamps)> {
+    let files = ConfigFiles::resolve(explicit.map(PathBuf::as_path)).ok()?;
+    let stamps = files
+        .paths()
+        .into_iter()
+        .map(|path| {
+            let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+            (path.to_path_buf(), modified)
+        })
+        .collect();
+    Some((files, stamps))
+}
+
+/// Polls the config files and reloads them when one changes, appears or goes away.
+pub struct ConfigWatcher {
+    explicit: Option<PathBuf>,
+    last: Stamps,
+}
+
+impl ConfigWatcher {
+    pub fn new(explicit: Option<PathBuf>) -> Self {
+        let last = stamps(explicit.as_ref()).map(|(_, stamps)| stamps).unwrap_or_default();
+        Self { explicit, last }
+    }
+
+    /// The freshly loaded mapping, or why it was rejected, when the files changed since the last call.
+    pub fn poll(&mut self) -> Option<(ConfigFiles, Result<YamlConfig, ConfigError>)> {
+        let (files, stamps) = stamps(self.explicit.as_ref())?;
+        if stamps == self.last {
+            return None;
+        }
+        self.last = stamps;
+        let result = files.load();
+        Some((files, result))
+    }
+}
+
+/// Watch the mapping in the background and publish every valid change to `shared`.
+/// An invalid file is reported and the previous mapping stays active.
+pub fn watch(explicit: Option<PathBuf>, shared: Arc<SharedConfig>) {
+    let mut watcher = ConfigWatcher::new(explicit);
+    thread::spawn(move || loop {
+        thread::sleep(CONFIG_POLL_INTERVAL);
+        match watcher.poll() {
+            Some((files, Ok(config))) => {
+                println!("✓ Reloaded config {}", files);
+                shared.replace(config);
+            }
+            Some((_, Err(e))) => eprintln!("{}\nKeeping the previous mapping.", e),
+            None => {}
+        }
+    });
+}
@@ -0,0 +1,132 @@
+use crate::conf::{YamlButton, YamlCurve};
+
+/// Default width of the `CenterDetent` flat zone, as a fraction of the travel.
+pub const DEFAULT_DETENT: f32 = 0.1;
+const MAX_7BIT: f64 = 127.0;
+const MAX_14BIT: f64 = 16383.0;
+
+/// A knob's transfer function from position to output value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Response {
+    curve: YamlCurve,
+    detent: f64,
+    table: Vec<u8>,
+    /// Output range as fractions of full scale.
+    min: f64,
+    max: f64,
+    invert: bool,
+}
+
+impl Default for Response {
+    fn default() -> Self {
+        Self {
+            curve: YamlCurve::Linear,
+            detent: DEFAULT_DETENT as f64,
+            table: Vec::new(),
+            min: 0.0,
+            max: 1.0,
+            invert: false,
+        }
+    }
+}
+
+impl Response {
+    pub fn from_yaml(yaml_button: &YamlButton) -> Self {
+        Self {
+            curve: yaml_button.curve.unwrap_or_default(),
+            detent: yaml_button.detent.unwrap_or(DEFAULT_DETENT) as f64,
+            table: yaml_button.curve_table.clone().unwrap_or_default(),
+            min: yaml_button.min.unwrap_or(0) as f64 / MAX_7BIT,
+            max: yaml_button.max.unwrap_or(127) as f64 / MAX_7BIT,
+            invert: yaml_button.invert.unwrap_or(false),
+        }
+    }
+
+    /// Curve applied to a position (0.0-1.0), before the output range.
+    fn shape(&self, position: f64) -> f64 {
+        let x = position.clamp(0.0, 1.0);
+        let x = if self.invert { 1.0 - x } else { x };
+        let y = match self.curve {
+            YamlCurve::Linear => x,
+            YamlCurve::Log => (1.0 + 9.0 * x).log10(),
+            YamlCurve::Exp => (10f64.powf(x) - 1.0) / 9.0,
+            YamlCurve::SCurve => x * x * (3.0 - 2.0 * x),
+            YamlCurve::CenterDetent => {
+                let half = (self.detent / 2.0).clamp(0.0, 0.49);
+                if (x - 0.5).abs() <= half {
+                    0.5
+                } else if x < 0.5 {
+                    x / (0.5 - half) * 0.5
+                } else {
+                    0.5 + (x - 0.5 - half) / (0.5 - half) * 0.5
+                }
+            }
+            YamlCurve::Table => match self.table.len() {
+                0 => x,
+                1 => self.table[0] as f64 / MAX_7BIT,
+                n => {
+                    let t = x * (n - 1) as f64;
+                    let i = (t.floor() as usize).min(n - 2);
+                    let (a, b) = (self.table[i] as f64, self.table[i + 1] as f64);
+                    (a + (b - a) * (t - i as f64)) / MAX_7BIT
+                }
+            },
+        };
+        self.min + y.clamp(0.0, 1.0) * (self.max - self.min)
+    }
+
+    /// 7-bit output for a knob position.
+    pub fn value(&self, position: f64) -> u8 {
+        (self.shape(position) * MAX_7BIT).round() as u8
+    }
+
+    /// 14-bit output for a knob position.
+    pub fn value14(&self, position: f64) -> u16 {
+        (self.shape(position) * MAX_14BIT).round() as u16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::knob_value;
+
+    fn response(yaml: &str) -> Response {
+        let button: YamlButton = serde_yaml::from_str(&format!(
+            "{{ name: K, type: Knob, read_i: 1, read_j: 2, midi_ctrl_ch: 0, {} }}",
+            yaml
+        ))
+        .unwrap();
+        Response::from_yaml(&button)
+    }
+
+    #[test]
+    fn linear_matches_the_plain_scaling() {
+        let linear = Response::default();
+        for raw in 0..=0x0FFFu16 {
+            let report = [0, (raw & 0xFF) as u8, (raw >> 8) as u8];
+            assert_eq!(linear.value(raw as f64 / 4095.0), knob_value(&report, 1, 2), "raw {}", raw);
+        }
+        assert_eq!(linear.value14(1.0), 16383);
+    }
+
+    #[test]
+    fn curves_ranges_and_inversion() {
+        let detent = response("curve: CenterDetent, detent: 0.2");
+        assert_eq!(detent.value(0.45), 64);
+        assert_eq!(detent.value(0.55), 64);
+        assert_eq!(detent.value(1.0), 127);
+
+        assert!(response("curve: Log").value(0.5) > 80);
+        assert!(response("curve: Exp").value(0.5) < 40);
+        assert_eq!(response("curve: SCurve").value(0.5), 64);
+
+        let table = response("curve: Table, curve_table: [0, 100, 127]");
+        assert_eq!(table.value(0.25), 50);
+        assert_eq!(table.value(0.5), 100);
+
+        let ranged = response("min: 20, max: 100, invert: true");
+        assert_eq!(ranged.value(0.0), 100);
+        assert_eq!(ranged.value(1.0), 20);
+    }
+}
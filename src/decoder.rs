@@ -0,0 +1,208 @@
+use std::fmt;
+
+use crate::x1_board::{ButtonType, X1mk1Board};
+
+/// Byte layout of an input report.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// 64-byte hidapi report: button `read_i` is a byte index into the report.
+    Hid,
+    /// 24-byte libusb bulk report: button bytes start at offset 1, so `read_i` is relative to that.
+    Usb,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ControlEvent {
+    ButtonDown { name: String },
+    ButtonUp { name: String },
+    /// `raw` is the 12-bit pot value (or the 4-bit value for single-byte knobs), `value` the 0-127 scaling.
+    KnobMoved { name: String, raw: u16, value: u8 },
+    /// `raw` is the new 4-bit encoder position, `delta` the signed number of detents moved.
+    EncoderStep { name: String, raw: u8, delta: i8 },
+}
+
+impl ControlEvent {
+    pub fn name(&self) -> &str {
+        match self {
+            ControlEvent::ButtonDown { name }
+            | ControlEvent::ButtonUp { name }
+            | ControlEvent::KnobMoved { name, .. }
+            | ControlEvent::EncoderStep { name, .. } => name,
+        }
+    }
+}
+
+impl fmt::Display for ControlEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ControlEvent::ButtonDown { name } => write!(f, "{} down", name),
+            ControlEvent::ButtonUp { name } => write!(f, "{} up", name),
+            ControlEvent::KnobMoved { name, raw, value } => write!(f, "{} {} (raw {})", name, value, raw),
+            ControlEvent::EncoderStep { name, raw, delta } => write!(f, "{} {:+} (pos {})", name, delta, raw),
+        }
+    }
+}
+
+/// The 64 data bytes of a hidapi read: a leading report ID is dropped from 65-byte reads,
+/// shorter reads are zero-padded.
+pub fn hid_payload(report: &[u8]) -> [u8; 64] {
+    let mut buf = [0u8; 64];
+    if report.len() >= 65 {
+        buf.copy_from_slice(&report[1..65]);
+    } else {
+        let len = report.len().min(64);
+        buf[..len].copy_from_slice(&report[..len]);
+    }
+    buf
+}
+
+pub fn button_state(report: &[u8], format: ReportFormat, read_i: u8, read_j: u8) -> bool {
+    let idx = match format {
+        ReportFormat::Hid => read_i as usize,
+        ReportFormat::Usb => read_i as usize + 1,
+    };
+    report.get(idx).map(|byte| (byte >> read_j) & 1 != 0).unwrap_or(false)
+}
+
+/// Raw knob reading: a 12-bit value from low byte `read_i` and high nibble `read_j`,
+/// or a 4-bit value when both point at the same byte.
+pub fn knob_raw(report: &[u8], read_i: u8, read_j: u8) -> u16 {
+    let i = report.get(read_i as usize).copied().unwrap_or(0);
+    let j = report.get(read_j as usize).copied().unwrap_or(0);
+    if read_i == read_j {
+        (i & 0x0F) as u16
+    } else {
+        (((j & 0x0F) as u16) << 8) | i as u16
+    }
+}
+
+/// Highest raw reading of a knob: 12 bits, or 4 for single-byte knobs.
+pub fn knob_full_scale(read_i: u8, read_j: u8) -> u16 {
+    if read_i == read_j {
+        0x0F
+    } else {
+        0x0FFF
+    }
+}
+
+/// Knob reading scaled to 0-127.
+pub fn knob_value(report: &[u8], read_i: u8, read_j: u8) -> u8 {
+    let raw = knob_raw(report, read_i, read_j);
+    let full_scale = knob_full_scale(read_i, read_j);
+    ((raw as f32 / full_scale as f32) * 127.0).round().min(127.0) as u8
+}
+
+/// 4-bit encoder position: `'s'` is the low nibble of byte `read_i`, `'e'` the high nibble.
+pub fn encoder_position(report: &[u8], read_i: u8, read_pos: char) -> u8 {
+    let byte = report.get(read_i as usize).copied().unwrap_or(0);
+    match read_pos {
+        'e' => byte >> 4,
+        _ => byte & 0x0F,
+    }
+}
+
+/// Positions of the 4-bit encoder counter.
+const ENCODER_POSITIONS: i8 = 16;
+
+/// Signed detents between two 4-bit encoder positions, taking the shorter way round the counter:
+/// -8 to +7, positive when the position increases. A spin of 8 or more detents between two
+/// reports cannot be told apart from a shorter one the other way.
+pub fn encoder_delta(prev: u8, curr: u8) -> i8 {
+    let diff = (curr as i8 - prev as i8).rem_euclid(ENCODER_POSITIONS);
+    if diff >= ENCODER_POSITIONS / 2 {
+        diff - ENCODER_POSITIONS
+    } else {
+        diff
+    }
+}
+
+/// Compare two reports and list every control whose reading changed, sorted by control name.
+pub fn decode(prev: &[u8], curr: &[u8], format: ReportFormat, board: &X1mk1Board) -> Vec<ControlEvent> {
+    let mut events = Vec::new();
+    for (name, control) in &board.buttons {
+        match control {
+            ButtonType::Toggle(b) | ButtonType::Hold(b) | ButtonType::Hotcue(b) => {
+                let was = button_state(prev, format, b.read_i, b.read_j);
+                let is = button_state(curr, format, b.read_i, b.read_j);
+                if is && !was {
+                    events.push(ControlEvent::ButtonDown { name: name.clone() });
+                } else if was && !is {
+                    events.push(ControlEvent::ButtonUp { name: name.clone() });
+                }
+            }
+            ButtonType::Knob(k) => {
+                let raw = knob_raw(curr, k.read_i, k.read_j);
+                if raw != knob_raw(prev, k.read_i, k.read_j) {
+                    let value = knob_value(curr, k.read_i, k.read_j);
+                    events.push(ControlEvent::KnobMoved { name: name.clone(), raw, value });
+                }
+            }
+            ButtonType::Encoder(e) => {
+                let was = encoder_position(prev, e.read_i, e.read_pos);
+                let raw = encoder_position(curr, e.read_i, e.read_pos);
+                let delta = encoder_delta(was, raw);
+                if delta != 0 {
+                    events.push(ControlEvent::EncoderStep { name: name.clone(), raw, delta });
+                }
+            }
+        }
+    }
+    events.sort_by(|a, b| a.name().cmp(b.name()));
+    events
+}
+
+/// Stateful wrapper around [`decode`] that remembers the previous report.
+/// The first report only establishes the baseline and produces no events.
+pub struct ReportDecoder {
+    format: ReportFormat,
+    prev: Option<Vec<u8>>,
+}
+
+impl ReportDecoder {
+    pub fn new(format: ReportFormat) -> Self {
+        Self { format, prev: None }
+    }
+
+    pub fn decode(&mut self, report: &[u8], board: &X1mk1Board) -> Vec<ControlEvent> {
+        let events = match &self.prev {
+            Some(prev) => decode(prev, report, self.format, board),
+            None => Vec::new(),
+        };
+        self.prev = Some(report.to_vec());
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn encoder_delta_recovers_every_short_spin(prev in 0u8..16, steps in -8i8..8) {
+            let curr = (prev as i8 + steps).rem_euclid(16) as u8;
+            prop_assert_eq!(encoder_delta(prev, curr), steps);
+        }
+
+        #[test]
+        fn encoder_delta_is_antisymmetric(prev in 0u8..16, curr in 0u8..16) {
+            let forward = encoder_delta(prev, curr);
+            if forward != -8 {
+                prop_assert_eq!(encoder_delta(curr, prev), -forward);
+            }
+        }
+
+        #[test]
+        fn encoder_deltas_add_up_along_a_spin(start in 0u8..16, steps in prop::collection::vec(-7i8..=7, 0..64)) {
+            let mut position = start;
+            let mut total: i32 = 0;
+            for step in &steps {
+                let next = (position as i8 + step).rem_euclid(16) as u8;
+                total += encoder_delta(position, next) as i32;
+                position = next;
+            }
+            prop_assert_eq!(total, steps.iter().map(|&s| s as i32).sum::<i32>());
+        }
+    }
+}
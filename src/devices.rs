@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::conf::YamlDevice;
+
+/// Port name of the first controller; further controllers get a suffix.
+pub const BASE_PORT_NAME: &str = "EASY KONTROL X1";
+
+/// What the menu bar / status output shows for one controller.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeviceStatus {
+    /// Name of the device's virtual MIDI ports.
+    pub name: String,
+    pub connected: bool,
+}
+
+/// Device id (serial) to status, sent to the UI on every change.
+pub type DeviceStatusMap = HashMap<String, DeviceStatus>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PortSuffix {
+    /// "EASY KONTROL X1", "EASY KONTROL X1 2", ... in order of first connection.
+    Index,
+    /// "EASY KONTROL X1 <serial>".
+    Serial,
+}
+
+/// Hands out virtual port names. A device keeps its name for the lifetime of the driver,
+/// so a reconnect reuses the port name the DJ software already knows.
+#[derive(Default)]
+pub struct PortNames {
+    indices: Mutex<HashMap<String, usize>>,
+}
+
+impl PortNames {
+    /// The `port_name` alias from the config's `devices:` section, else the default name for `suffix`.
+    pub fn name_for(&self, serial: &str, devices: &[YamlDevice], suffix: PortSuffix) -> String {
+        if let Some(alias) = devices
+            .iter()
+            .find(|d| d.serial == serial)
+            .and_then(|d| d.port_name.clone())
+        {
+            return alias;
+        }
+        match suffix {
+            PortSuffix::Serial => format!("{} {}", BASE_PORT_NAME, serial),
+            PortSuffix::Index => match self.index_for(serial) {
+                1 => BASE_PORT_NAME.to_string(),
+                idx => format!("{} {}", BASE_PORT_NAME, idx),
+            },
+        }
+    }
+
+    fn index_for(&self, serial: &str) -> usize {
+        let mut indices = self.indices.lock().unwrap();
+        if let Some(&idx) = indices.get(serial) {
+            return idx;
+        }
+        let idx = (1..).find(|i| !indices.values().any(|v| v == i)).unwrap();
+        indices.insert(serial.to_string(), idx);
+        idx
+    }
+}
@@ -0,0 +1,135 @@
+use std::time::Instant;
+
+use crate::conf::{YamlButton, YamlEncoding, YamlMessageType};
+use crate::midi_message::Message;
+
+/// Detents closer together than this turn at full `acceleration`.
+const ACCEL_FAST_MS: f64 = 20.0;
+/// Detents further apart than this move one step each.
+const ACCEL_SLOW_MS: f64 = 150.0;
+
+/// How an encoder's detents are turned into values.
+#[derive(Clone, Debug)]
+pub struct EncoderOutput {
+    encoding: YamlEncoding,
+    /// `Notes`: note sent for counter-clockwise detents.
+    note_down: Option<u8>,
+    min: u8,
+    max: u8,
+    /// `Absolute`: the value last sent.
+    pub value: u8,
+    /// Most steps one detent moves when turned fast; 1 turns acceleration off.
+    acceleration: u8,
+    last_turn: Option<Instant>,
+}
+
+impl EncoderOutput {
+    pub fn from_yaml(yaml_button: &YamlButton) -> Self {
+        let min = yaml_button.min.unwrap_or(0);
+        Self {
+            encoding: yaml_button.encoding.unwrap_or_default(),
+            note_down: yaml_button.note_down,
+            min,
+            max: yaml_button.max.unwrap_or(127),
+            value: min,
+            acceleration: yaml_button.acceleration.unwrap_or(1).max(1),
+            last_turn: None,
+        }
+    }
+
+    /// Keep the `Absolute` value of the encoder this one replaces.
+    pub fn carry_from(&mut self, old: &EncoderOutput) {
+        self.value = old.value.clamp(self.min, self.max);
+        self.last_turn = old.last_turn;
+    }
+
+    /// Steps for `delta` detents turned at `now`, scaled up when they follow each other quickly.
+    fn steps(&mut self, delta: i8, now: Instant) -> i16 {
+        let interval = self.last_turn.map(|t| now.duration_since(t).as_secs_f64() * 1000.0);
+        self.last_turn = Some(now);
+        let speed = match interval {
+            Some(ms) => ((ACCEL_SLOW_MS - ms) / (ACCEL_SLOW_MS - ACCEL_FAST_MS)).clamp(0.0, 1.0),
+            None => 0.0,
+        };
+        let scale = 1.0 + (self.acceleration - 1) as f64 * speed;
+        (delta as f64 * scale).round() as i16
+    }
+
+    /// Messages for `delta` detents (positive is clockwise) on `channel` and `number`.
+    pub fn turn(&mut self, message: &Message, channel: u8, number: u8, delta: i8, now: Instant) -> Vec<Vec<u8>> {
+        if delta == 0 {
+            return Vec::new();
+        }
+        let steps = self.steps(delta, now);
+        let n = steps.unsigned_abs().min(63) as u8;
+        let relative = |value: u8| message.value(channel, number, value);
+        match self.encoding {
+            YamlEncoding::TwosComplement if steps > 0 => relative(n),
+            YamlEncoding::TwosComplement => relative(128 - n),
+            YamlEncoding::BinaryOffset if steps > 0 => relative(64 + n),
+            YamlEncoding::BinaryOffset => relative(64 - n),
+            YamlEncoding::SignMagnitude if steps > 0 => relative(n),
+            YamlEncoding::SignMagnitude => relative(64 | n),
+            YamlEncoding::Notes => {
+                let note = if steps > 0 { number } else { self.note_down.unwrap_or(number.wrapping_add(1) & 0x7F) };
+                let notes = Message { kind: YamlMessageType::Note, ..*message };
+                (0..n)
+                    .flat_map(|_| [notes.button(channel, note, true), notes.button(channel, note, false)])
+                    .flatten()
+                    .collect()
+            }
+            YamlEncoding::Absolute => {
+                let value = (self.value as i16 + steps).clamp(self.min as i16, self.max as i16) as u8;
+                if value == self.value {
+                    return Vec::new();
+                }
+                self.value = value;
+                message.value(channel, number, value)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn output(yaml: &str) -> EncoderOutput {
+        let button: YamlButton = serde_yaml::from_str(&format!(
+            "{{ name: E, type: Encoder, read_i: 17, read_pos: 's', midi_ctrl_ch: 20, {} }}",
+            yaml
+        ))
+        .unwrap();
+        EncoderOutput::from_yaml(&button)
+    }
+
+    #[test]
+    fn encodes_each_mode() {
+        let cc = Message::default();
+        let now = Instant::now();
+        let mut twos = output("encoding: TwosComplement");
+        assert_eq!(twos.turn(&cc, 0, 20, 1, now), vec![vec![0xB0, 20, 1]]);
+        assert_eq!(twos.turn(&cc, 0, 20, -1, now + Duration::from_secs(1)), vec![vec![0xB0, 20, 127]]);
+        let mut offset = output("encoding: BinaryOffset");
+        assert_eq!(offset.turn(&cc, 0, 20, -1, now), vec![vec![0xB0, 20, 63]]);
+        let mut sign = output("encoding: SignMagnitude");
+        assert_eq!(sign.turn(&cc, 0, 20, -1, now), vec![vec![0xB0, 20, 65]]);
+        let mut notes = output("encoding: Notes, note_down: 30");
+        assert_eq!(notes.turn(&cc, 1, 20, -1, now), vec![vec![0x91, 30, 127], vec![0x81, 30, 0]]);
+
+        let mut absolute = output("encoding: Absolute, min: 10, max: 11");
+        assert_eq!(absolute.turn(&cc, 0, 20, 1, now), vec![vec![0xB0, 20, 11]]);
+        assert!(absolute.turn(&cc, 0, 20, 1, now + Duration::from_secs(1)).is_empty());
+    }
+
+    #[test]
+    fn fast_turns_accelerate() {
+        let cc = Message::default();
+        let now = Instant::now();
+        let mut fast = output("encoding: BinaryOffset, acceleration: 4");
+        assert_eq!(fast.turn(&cc, 0, 20, 1, now), vec![vec![0xB0, 20, 65]]);
+        assert_eq!(fast.turn(&cc, 0, 20, 1, now + Duration::from_millis(10)), vec![vec![0xB0, 20, 68]]);
+        assert_eq!(fast.turn(&cc, 0, 20, 1, now + Duration::from_millis(500)), vec![vec![0xB0, 20, 65]]);
+    }
+}
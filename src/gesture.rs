@@ -0,0 +1,198 @@
+use std::time::{Duration, Instant};
+
+use crate::conf::{YamlGestures, YamlOutput};
+
+/// Hold time of a long press, unless a button sets `long_press_ms`.
+const LONG_PRESS_MS: u64 = 800;
+/// A long press sends on the CC this far above the button's own, unless it says otherwise.
+const LONG_PRESS_OFFSET: u8 = 32;
+const DEFAULT_DOUBLE_TAP_MS: u64 = 300;
+const DEFAULT_REPEAT_DELAY_MS: u64 = 400;
+
+/// What a button's gestures decided to send.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Gesture {
+    /// The button's own output, as a press and release.
+    Tap,
+    LongPress,
+    DoubleTap,
+}
+
+/// Recognizer for the press gestures of one button.
+#[derive(Clone, Debug)]
+pub struct Gestures {
+    long_press: Option<(Duration, YamlOutput)>,
+    /// False when a long press only holds the button and sends nothing.
+    long_press_sends: bool,
+    double_tap: Option<(Duration, YamlOutput)>,
+    /// Delay before the first repeat, then the interval between repeats.
+    repeat: Option<(Duration, Duration)>,
+    pressed_at: Option<Instant>,
+    long_press_sent: bool,
+    /// Release of a tap that may still become a double tap.
+    pending_tap: Option<Instant>,
+    next_repeat: Option<Instant>,
+}
+
+impl Gestures {
+    pub fn from_yaml(yaml_gestures: &YamlGestures) -> Self {
+        let ms = Duration::from_millis;
+        let long_press = (yaml_gestures.long_press.is_some() || yaml_gestures.long_press_ms.is_some()).then(|| {
+            let hold = yaml_gestures.long_press_ms.unwrap_or(LONG_PRESS_MS);
+            (ms(hold), yaml_gestures.long_press.unwrap_or_default())
+        });
+        let double_tap = yaml_gestures.double_tap.map(|output| {
+            (ms(yaml_gestures.double_tap_ms.unwrap_or(DEFAULT_DOUBLE_TAP_MS)), output)
+        });
+        let repeat = yaml_gestures.repeat_ms.map(|interval| {
+            (ms(yaml_gestures.repeat_delay_ms.unwrap_or(DEFAULT_REPEAT_DELAY_MS)), ms(interval))
+        });
+        Self {
+            long_press,
+            long_press_sends: true,
+            double_tap,
+            repeat,
+            pressed_at: None,
+            long_press_sent: false,
+            pending_tap: None,
+            next_repeat: None,
+        }
+    }
+
+    /// The long press that `long_press` modifiers give buttons without their own gestures.
+    pub fn layer_long_press() -> Self {
+        Self::from_yaml(&YamlGestures { long_press_ms: Some(LONG_PRESS_MS), ..Default::default() })
+    }
+
+    /// A momentary modifier button: a tap sends the button, a hold only holds the layer.
+    pub fn modifier() -> Self {
+        Self { long_press_sends: false, ..Self::layer_long_press() }
+    }
+
+    pub fn is_pressed(&self) -> bool {
+        self.pressed_at.is_some()
+    }
+
+    /// Take over a press in progress from the recognizer a reload replaced.
+    pub fn carry_press_from(&mut self, old: &Gestures) {
+        self.pressed_at = old.pressed_at;
+        self.long_press_sent = old.long_press_sent;
+    }
+
+    pub fn press(&mut self, now: Instant) -> Vec<Gesture> {
+        self.pressed_at = Some(now);
+        self.long_press_sent = false;
+        match self.repeat {
+            Some((delay, _)) => {
+                self.next_repeat = Some(now + delay);
+                vec![Gesture::Tap]
+            }
+            None => Vec::new(),
+        }
+    }
+
+    pub fn release(&mut self, now: Instant) -> Vec<Gesture> {
+        let held = self.pressed_at.take().map(|t| now.duration_since(t)).unwrap_or_default();
+        self.next_repeat = None;
+        if self.repeat.is_some() || self.long_press_sent {
+            return Vec::new();
+        }
+        if self.long_press.is_some_and(|(hold, _)| held >= hold) {
+            return if self.long_press_sends { vec![Gesture::LongPress] } else { Vec::new() };
+        }
+        if let Some((window, _)) = self.double_tap {
+            if self.pending_tap.take().is_some_and(|t| now.duration_since(t) <= window) {
+                return vec![Gesture::DoubleTap];
+            }
+            self.pending_tap = Some(now);
+            return Vec::new();
+        }
+        vec![Gesture::Tap]
+    }
+
+    /// Gestures that time alone decides: a long press reached while held, a repeat that is due,
+    /// or a tap whose double-tap window ran out.
+    pub fn tick(&mut self, now: Instant) -> Vec<Gesture> {
+        let mut fired = Vec::new();
+        if let (Some(pressed), Some((hold, _))) = (self.pressed_at, self.long_press) {
+            if !self.long_press_sent && now.duration_since(pressed) >= hold {
+                self.long_press_sent = true;
+                if self.long_press_sends {
+                    fired.push(Gesture::LongPress);
+                }
+            }
+        }
+        if let (Some(due), Some((_, interval))) = (self.next_repeat, self.repeat) {
+            if now >= due {
+                self.next_repeat = Some(now + interval);
+                fired.push(Gesture::Tap);
+            }
+        }
+        if let (Some(released), Some((window, _))) = (self.pending_tap, self.double_tap) {
+            if now.duration_since(released) > window {
+                self.pending_tap = None;
+                fired.push(Gesture::Tap);
+            }
+        }
+        fired
+    }
+
+    /// 0-based channel and number `gesture` sends on, given those of a tap.
+    pub fn address(&self, gesture: Gesture, channel: u8, number: u8) -> (u8, u8) {
+        let (output, default_number) = match (gesture, self.long_press, self.double_tap) {
+            (Gesture::LongPress, Some((_, output)), _) => (output, number.saturating_add(LONG_PRESS_OFFSET)),
+            (Gesture::DoubleTap, _, Some((_, output))) => (output, number),
+            _ => return (channel, number),
+        };
+        let channel = output.channel.map_or(channel, |c| c.saturating_sub(1));
+        (channel, output.cc.unwrap_or(default_number))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gestures(yaml: &str) -> Gestures {
+        Gestures::from_yaml(&serde_yaml::from_str(yaml).unwrap())
+    }
+
+    #[test]
+    fn tells_taps_from_holds_double_taps_and_repeats() {
+        let t0 = Instant::now();
+        let at = |ms| t0 + Duration::from_millis(ms);
+
+        let mut hold = gestures("{ long_press_ms: 500 }");
+        hold.press(t0);
+        assert_eq!(hold.release(at(100)), vec![Gesture::Tap]);
+        hold.press(at(200));
+        assert_eq!(hold.tick(at(700)), vec![Gesture::LongPress]);
+        assert!(hold.release(at(900)).is_empty());
+        assert_eq!(hold.address(Gesture::LongPress, 0, 10), (0, 42));
+
+        let mut double = gestures("{ double_tap: { cc: 90 }, double_tap_ms: 300 }");
+        double.press(t0);
+        assert!(double.release(at(50)).is_empty());
+        double.press(at(150));
+        assert_eq!(double.release(at(200)), vec![Gesture::DoubleTap]);
+        double.press(at(1000));
+        assert!(double.release(at(1050)).is_empty());
+        assert_eq!(double.tick(at(1400)), vec![Gesture::Tap]);
+        assert_eq!(double.address(Gesture::DoubleTap, 1, 10), (1, 90));
+
+        let mut repeat = gestures("{ repeat_ms: 100, repeat_delay_ms: 400 }");
+        assert_eq!(repeat.press(t0), vec![Gesture::Tap]);
+        assert!(repeat.tick(at(300)).is_empty());
+        assert_eq!(repeat.tick(at(400)), vec![Gesture::Tap]);
+        assert_eq!(repeat.tick(at(500)), vec![Gesture::Tap]);
+        assert!(repeat.release(at(550)).is_empty());
+        assert!(repeat.tick(at(700)).is_empty());
+
+        let mut modifier = Gestures::modifier();
+        modifier.press(t0);
+        assert_eq!(modifier.release(at(100)), vec![Gesture::Tap]);
+        modifier.press(at(200));
+        assert!(modifier.tick(at(1100)).is_empty());
+        assert!(modifier.release(at(1200)).is_empty());
+    }
+}
@@ -0,0 +1,91 @@
+//! Golden MIDI-output tests: every `tests/golden/<name>.x1cap` capture is replayed through the HID
+//! pipeline and the emitted messages are compared with `tests/golden/<name>.midi`.
+//!
+//! A capture runs against `tests/golden/<name>.yml` when present, otherwise against the shipped
+//! `board.yml`. Run with `UPDATE_GOLDEN=1` to rewrite the expected files (leading `#` comment
+//! lines are kept).
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::capture::{Capture, CAPTURE_EXTENSION};
+use crate::conf::YamlConfig;
+use crate::validate::load_config_file;
+use crate::x1_process_hid::tests::handler;
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden")
+}
+
+fn load_config(capture_path: &Path) -> YamlConfig {
+    let override_path = capture_path.with_extension("yml");
+    let path = if override_path.exists() {
+        override_path
+    } else {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("board.yml")
+    };
+    load_config_file(&path).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// One line per emitted message: milliseconds since capture start, then the message bytes in hex.
+fn render(capture_path: &Path) -> String {
+    let capture = Capture::load(capture_path).unwrap_or_else(|e| panic!("{}: {}", capture_path.display(), e));
+    let (mut x1, sink) = handler(load_config(capture_path));
+    let start = Instant::now();
+    let mut out = String::new();
+    for record in &capture.records {
+        x1.process_report(&record.report, start + record.at);
+        for message in sink.take() {
+            let bytes: Vec<String> = message.iter().map(|b| format!("{:02x}", b)).collect();
+            writeln!(out, "{:>7} {}", record.at.as_millis(), bytes.join(" ")).unwrap();
+        }
+    }
+    out
+}
+
+fn strip_comments(text: &str) -> String {
+    text.lines()
+        .filter(|line| !line.starts_with('#') && !line.trim().is_empty())
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+#[test]
+fn golden_midi_output() {
+    let mut captures: Vec<PathBuf> = fs::read_dir(golden_dir())
+        .expect("tests/golden is missing")
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().and_then(|e| e.to_str()) == Some(CAPTURE_EXTENSION))
+        .collect();
+    captures.sort();
+    assert!(!captures.is_empty(), "no captures in tests/golden");
+
+    let update = std::env::var("UPDATE_GOLDEN").is_ok();
+    let mut failures = Vec::new();
+    for capture_path in &captures {
+        let actual = render(capture_path);
+        let expected_path = capture_path.with_extension("midi");
+        let existing = fs::read_to_string(&expected_path).unwrap_or_default();
+        if update {
+            let header: String = existing
+                .lines()
+                .take_while(|line| line.starts_with('#'))
+                .map(|line| format!("{}\n", line))
+                .collect();
+            fs::write(&expected_path, header + &actual).unwrap();
+            continue;
+        }
+        let expected = strip_comments(&existing);
+        if actual != expected {
+            failures.push(format!(
+                "{}\n--- expected\n{}--- actual\n{}",
+                capture_path.display(),
+                expected,
+                actual
+            ));
+        }
+    }
+    assert!(failures.is_empty(), "golden MIDI mismatch (rerun with UPDATE_GOLDEN=1 if intended):\n{}", failures.join("\n"));
+}
@@ -1,39 +1,84 @@
+use std::ffi::{CStr, CString};
+
 use hidapi::HidApi;
 
-const VENDOR_ID: u16 = 0x17cc;
-const PRODUCT_ID_MK1: u16 = 0x2305;
-const PRODUCT_ID_MK2: u16 = 0x1220;
+pub const VENDOR_ID: u16 = 0x17cc;
+pub const PRODUCT_ID_MK1: u16 = 0x2305;
+pub const PRODUCT_ID_MK2: u16 = 0x1220;
+
+/// A connected X1 as reported by hidapi, without opening it.
+pub struct HidDeviceInfo {
+    pub product_id: u16,
+    pub serial_number: Option<String>,
+    pub path: CString,
+    /// Key the driver uses for this device: the serial, or a path-derived name when there is none.
+    pub id: String,
+}
 
 pub struct HidDevice {
     pub handle: hidapi::HidDevice,
+    /// Same as [`HidDeviceInfo::id`].
     pub serial_number: String,
 }
 
+pub fn model_name(product_id: u16) -> &'static str {
+    match product_id {
+        PRODUCT_ID_MK1 => "X1 MK1",
+        PRODUCT_ID_MK2 => "X1 MK2",
+        _ => "X1",
+    }
+}
+
+/// Identity for devices that report no serial number. The OS path is unique among attached
+/// devices and stays the same while the controller remains on the same port, so two serial-less
+/// X1s no longer collide under one name.
+fn path_identity(product_id: u16, path: &CStr) -> String {
+    let path = path.to_string_lossy();
+    let short = path.rsplit(['/', ':']).next().unwrap_or(&path);
+    format!("{}-{}", model_name(product_id).replace(' ', "-"), short)
+}
+
+pub(crate) fn x1_infos(api: &HidApi) -> Vec<HidDeviceInfo> {
+    let mut infos: Vec<HidDeviceInfo> = Vec::new();
+    for info in api.device_list() {
+        if info.vendor_id() != VENDOR_ID || !matches!(info.product_id(), PRODUCT_ID_MK1 | PRODUCT_ID_MK2) {
+            continue;
+        }
+        // Some platforms list one entry per top-level collection; open each device once.
+        if infos.iter().any(|known| known.path.as_c_str() == info.path()) {
+            continue;
+        }
+        let serial_number = info.serial_number().filter(|s| !s.is_empty()).map(str::to_string);
+        let id = serial_number
+            .clone()
+            .unwrap_or_else(|| path_identity(info.product_id(), info.path()));
+        infos.push(HidDeviceInfo {
+            product_id: info.product_id(),
+            serial_number,
+            path: info.path().to_owned(),
+            id,
+        });
+    }
+    infos.sort_by(|a, b| a.path.cmp(&b.path));
+    infos
+}
+
 impl HidDevice {
-    /// Try to find and open an NI X1 device using hidapi
+    /// List every attached X1 MK1/MK2 without opening it.
+    pub fn list() -> Result<Vec<HidDeviceInfo>, String> {
+        let api = HidApi::new().map_err(|e| format!("Failed to create HID API: {}", e))?;
+        Ok(x1_infos(&api))
+    }
+
+    /// Open every attached NI X1 by path using hidapi
     pub fn open() -> Result<Vec<HidDevice>, String> {
         let api = HidApi::new().map_err(|e| format!("Failed to create HID API: {}", e))?;
         let mut devices = Vec::new();
 
-        // Scan for MK1 and MK2 devices
-        for product_id in &[PRODUCT_ID_MK1, PRODUCT_ID_MK2] {
-            match api.open(VENDOR_ID, *product_id) {
-                Ok(device) => {
-                    let serial = device
-                        .get_serial_number_string()
-                        .ok()
-                        .flatten()
-                        .unwrap_or_else(|| "Unknown".to_string());
-                    println!("Opened HID device: vendor=0x{:04x} product=0x{:04x}", 
-                        VENDOR_ID, product_id);
-                    devices.push(HidDevice {
-                        handle: device,
-                        serial_number: serial,
-                    });
-                }
-                Err(_) => {
-                    // Device not found, which is fine
-                }
+        for info in x1_infos(&api) {
+            match Self::open_info(&api, info) {
+                Ok(device) => devices.push(device),
+                Err(e) => eprintln!("{}", e),
             }
         }
 
@@ -44,34 +89,20 @@ impl HidDevice {
         Ok(devices)
     }
 
-    /// Write LED data to the device via HID
-    pub fn write_leds(&self, data: &[u8; 32]) -> Result<(), String> {
-        let mut buf = vec![0u8; 33];
-        buf[0] = 0; // Report ID
-        buf[1..].copy_from_slice(data);
-        
-        match self.handle.write(&buf) {
-            Ok(n) => {
-                if n != buf.len() {
-                    eprintln!("Partial LED write: wrote {} bytes, expected {}", n, buf.len());
-                }
-                Ok(())
-            }
-            Err(e) => Err(format!("LED write error: {}", e)),
-        }
-    }
-
-    /// Read button/knob data from the device via HID
-    pub fn read_input(&self, buf: &mut [u8; 24], timeout_ms: i32) -> Result<usize, String> {
-        match self.handle.read_timeout(buf, timeout_ms) {
-            Ok(n) => {
-                if n > 0 {
-                    Ok(n)
-                } else {
-                    Err("No data".to_string())
-                }
-            }
-            Err(e) => Err(format!("Read error: {}", e)),
-        }
+    pub(crate) fn open_info(api: &HidApi, info: HidDeviceInfo) -> Result<HidDevice, String> {
+        let device = api
+            .open_path(&info.path)
+            .map_err(|e| format!("Could not open {}: {}", info.path.to_string_lossy(), e))?;
+        println!(
+            "Opened HID device: vendor=0x{:04x} product=0x{:04x} id={} path={}",
+            VENDOR_ID,
+            info.product_id,
+            info.id,
+            info.path.to_string_lossy()
+        );
+        Ok(HidDevice {
+            handle: device,
+            serial_number: info.id,
+        })
     }
 }
@@ -0,0 +1,87 @@
+use std::collections::HashSet;
+use std::ffi::CString;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use hidapi::HidApi;
+
+use crate::hid_device::{x1_infos, HidDevice};
+
+/// hidapi has no arrival callbacks, so the device list is re-read at this interval.
+pub const HID_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Marks a device path as owned by a running handler; dropping it lets the watcher reopen the path.
+pub struct DeviceClaim {
+    path: CString,
+    active: Arc<Mutex<HashSet<CString>>>,
+}
+
+impl Drop for DeviceClaim {
+    fn drop(&mut self) {
+        self.active.lock().unwrap().remove(&self.path);
+    }
+}
+
+/// Polling replacement for [`crate::usb_hotplug::HotPlugHandler`] on the hidapi path.
+/// Every X1 that is attached but has no live [`DeviceClaim`] is opened by [`HidWatcher::poll`],
+/// which covers devices plugged in after launch as well as reconnects after a read error.
+pub struct HidWatcher {
+    api: HidApi,
+    active: Arc<Mutex<HashSet<CString>>>,
+    present: HashSet<CString>,
+    failed: HashSet<CString>,
+}
+
+impl HidWatcher {
+    pub fn new() -> Result<Self, String> {
+        let api = HidApi::new().map_err(|e| format!("Failed to create HID API: {}", e))?;
+        Ok(Self {
+            api,
+            active: Arc::new(Mutex::new(HashSet::new())),
+            present: HashSet::new(),
+            failed: HashSet::new(),
+        })
+    }
+
+    /// Open every attached X1 that is not already claimed.
+    pub fn poll(&mut self) -> Vec<(HidDevice, DeviceClaim)> {
+        if let Err(e) = self.api.refresh_devices() {
+            eprintln!("[HID] refresh_devices failed: {}", e);
+            return Vec::new();
+        }
+        let infos = x1_infos(&self.api);
+        let present: HashSet<CString> = infos.iter().map(|info| info.path.clone()).collect();
+        for gone in self.present.difference(&present) {
+            println!("🟠 HID device left {}", gone.to_string_lossy());
+        }
+        self.failed.retain(|path| present.contains(path));
+        self.present = present;
+
+        let mut opened = Vec::new();
+        for info in infos {
+            if self.active.lock().unwrap().contains(&info.path) {
+                continue;
+            }
+            let path = info.path.clone();
+            match HidDevice::open_info(&self.api, info) {
+                Ok(device) => {
+                    println!("🟢 HID device arrived {}", device.serial_number);
+                    self.failed.remove(&path);
+                    self.active.lock().unwrap().insert(path.clone());
+                    let claim = DeviceClaim {
+                        path,
+                        active: Arc::clone(&self.active),
+                    };
+                    opened.push((device, claim));
+                }
+                Err(e) => {
+                    // Retried on every poll; only report the first failure.
+                    if self.failed.insert(path) {
+                        eprintln!("{}", e);
+                    }
+                }
+            }
+        }
+        opened
+    }
+}
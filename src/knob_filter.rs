@@ -0,0 +1,120 @@
+use std::collections::VecDeque;
+
+use crate::conf::{YamlButton, YamlSmoothing};
+
+/// Readings smoothed over when a knob has `smoothing` but no `smoothing_window`.
+pub const DEFAULT_SMOOTHING_WINDOW: u8 = 4;
+
+/// Jitter filter over a knob's raw readings: optional smoothing, then a deadband around the last
+/// accepted reading.
+#[derive(Clone, Debug)]
+pub struct KnobFilter {
+    /// Raw steps a reading has to move away from the accepted one to count.
+    deadband: u16,
+    smoothing: Option<YamlSmoothing>,
+    window: usize,
+    history: VecDeque<u16>,
+    ema: f64,
+    accepted: u16,
+}
+
+impl KnobFilter {
+    pub fn from_yaml(yaml_button: &YamlButton) -> Self {
+        Self {
+            deadband: yaml_button.deadband.unwrap_or(0),
+            smoothing: yaml_button.smoothing,
+            window: yaml_button.smoothing_window.unwrap_or(DEFAULT_SMOOTHING_WINDOW).max(1) as usize,
+            history: VecDeque::new(),
+            ema: 0.0,
+            accepted: 0,
+        }
+    }
+
+    /// Whether the knob filters its readings at all.
+    pub fn is_active(&self) -> bool {
+        self.deadband > 0 || self.smoothing.is_some()
+    }
+
+    pub fn accepted(&self) -> u16 {
+        self.accepted
+    }
+
+    /// Start over from `raw`, e.g. on the first report.
+    pub fn reset(&mut self, raw: u16) {
+        self.history.clear();
+        self.history.push_back(raw);
+        self.ema = raw as f64;
+        self.accepted = raw;
+    }
+
+    /// Filtered reading for a new raw one. The ends of the travel (0 and `full_scale`) always get
+    /// through so a deadband cannot keep a knob from reaching them.
+    pub fn update(&mut self, raw: u16, full_scale: u16) -> u16 {
+        let smoothed = match self.smoothing {
+            None => raw,
+            Some(YamlSmoothing::Average) => {
+                self.history.push_back(raw);
+                while self.history.len() > self.window {
+                    self.history.pop_front();
+                }
+                let sum: u32 = self.history.iter().map(|&r| r as u32).sum();
+                let len = self.history.len() as u32;
+                ((sum + len / 2) / len) as u16
+            }
+            Some(YamlSmoothing::Ema) => {
+                let alpha = 2.0 / (self.window as f64 + 1.0);
+                self.ema += alpha * (raw as f64 - self.ema);
+                self.ema.round() as u16
+            }
+        };
+        let at_end = smoothed == 0 || smoothed == full_scale;
+        if smoothed.abs_diff(self.accepted) > self.deadband || at_end {
+            self.accepted = smoothed;
+        }
+        self.accepted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(yaml: &str) -> KnobFilter {
+        let button: YamlButton = serde_yaml::from_str(&format!(
+            "{{ name: K, type: Knob, read_i: 1, read_j: 2, midi_ctrl_ch: 0, {} }}",
+            yaml
+        ))
+        .unwrap();
+        let mut filter = KnobFilter::from_yaml(&button);
+        filter.reset(2000);
+        filter
+    }
+
+    #[test]
+    fn deadband_holds_until_the_reading_moves_far_enough() {
+        let mut f = filter("deadband: 8");
+        assert_eq!(f.update(2008, 0x0FFF), 2000);
+        assert_eq!(f.update(1992, 0x0FFF), 2000);
+        assert_eq!(f.update(2009, 0x0FFF), 2009);
+        assert_eq!(f.update(0x0FFB, 0x0FFF), 0x0FFB);
+        assert_eq!(f.update(0x0FFF, 0x0FFF), 0x0FFF);
+    }
+
+    #[test]
+    fn smoothing_averages_flicker_and_settles() {
+        let mut average = filter("smoothing: Average, smoothing_window: 4");
+        let readings = [2001, 1999, 2001, 1999, 2001, 1999];
+        assert!(readings.iter().all(|&r| average.update(r, 0x0FFF).abs_diff(2000) <= 1));
+        for _ in 0..4 {
+            average.update(2100, 0x0FFF);
+        }
+        assert_eq!(average.accepted(), 2100);
+
+        let mut ema = filter("smoothing: Ema, smoothing_window: 3");
+        assert_eq!(ema.update(2100, 0x0FFF), 2050);
+        for _ in 0..20 {
+            ema.update(2100, 0x0FFF);
+        }
+        assert_eq!(ema.accepted(), 2100);
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::{BTreeMap, HashSet};
+
+use crate::conf::{YamlConfig, YamlLayerOutput, YamlModifier, YamlModifierMode, YamlOutput};
+
+/// Layer that enables `Hotcue` controls, disables `hotcue_ignore` ones and shows the hotcue pad LEDs.
+pub const HOTCUE_LAYER: &str = "hotcue";
+
+/// Per-layer output overrides of one control.
+pub type LayerOutputs = BTreeMap<String, YamlOutput>;
+
+pub fn layer_outputs(layers: &BTreeMap<String, YamlLayerOutput>) -> LayerOutputs {
+    layers.iter().map(|(layer, output)| (layer.clone(), output.output())).collect()
+}
+
+/// The config's modifiers and which of their layers are on.
+pub struct Layers {
+    modifiers: Vec<YamlModifier>,
+    active: HashSet<String>,
+}
+
+impl Layers {
+    pub fn from_yaml(yaml_config: &YamlConfig) -> Self {
+        Self {
+            modifiers: yaml_config.modifiers.clone(),
+            active: HashSet::new(),
+        }
+    }
+
+    /// Use the modifiers of a new mapping; layers it still defines stay on.
+    pub fn replace_modifiers(&mut self, yaml_config: &YamlConfig) {
+        self.modifiers = yaml_config.modifiers.clone();
+        let modifiers = &self.modifiers;
+        self.active.retain(|layer| modifiers.iter().any(|m| &m.layer == layer));
+    }
+
+    pub fn modifiers(&self) -> &[YamlModifier] {
+        &self.modifiers
+    }
+
+    /// The modifier bound to `button`, if any.
+    pub fn modifier(&self, button: &str) -> Option<&YamlModifier> {
+        self.modifiers.iter().find(|m| m.button == button)
+    }
+
+    pub fn is_active(&self, layer: &str) -> bool {
+        self.active.contains(layer)
+    }
+
+    pub fn active(&self) -> &HashSet<String> {
+        &self.active
+    }
+
+    /// Turn on the layers of a previous session that this mapping defines.
+    pub fn restore(&mut self, active: HashSet<String>) {
+        self.active = active;
+        let modifiers = &self.modifiers;
+        self.active.retain(|layer| modifiers.iter().any(|m| &m.layer == layer));
+    }
+
+    /// A modifier button went down; momentary layers turn on.
+    pub fn press(&mut self, button: &str) {
+        if let Some(m) = self.modifier(button).filter(|m| m.mode == YamlModifierMode::Momentary) {
+            self.active.insert(m.layer.clone());
+        }
+    }
+
+    /// A modifier button came up: momentary layers turn off and latching ones flip. Returns the
+    /// mode and whether the layer is now on, or `None` when `button` is no modifier.
+    pub fn release(&mut self, button: &str) -> Option<(YamlModifierMode, bool)> {
+        let m = self.modifier(button)?;
+        let (mode, layer) = (m.mode, m.layer.clone());
+        let on = match mode {
+            YamlModifierMode::Momentary => false,
+            YamlModifierMode::Latching => !self.active.contains(&layer),
+        };
+        if on {
+            self.active.insert(layer);
+        } else {
+            self.active.remove(&layer);
+        }
+        Some((mode, on))
+    }
+
+    /// Turn off momentary layers whose button is no longer `held`, e.g. after a reconnect.
+    pub fn release_unheld(&mut self, held: impl Fn(&str) -> bool) {
+        for m in &self.modifiers {
+            if m.mode == YamlModifierMode::Momentary && !held(&m.button) {
+                self.active.remove(&m.layer);
+            }
+        }
+    }
+
+    /// An active layer asks for long presses.
+    pub fn long_press(&self) -> bool {
+        self.modifiers.iter().any(|m| m.long_press && self.active.contains(&m.layer))
+    }
+
+    /// 0-based channel and CC (or note, program, parameter) number a control sends on given the
+    /// active layers; `channel` and `number` are what it uses without any.
+    pub fn output(&self, overrides: &LayerOutputs, channel: u8, number: u8) -> (u8, u8) {
+        for m in self.modifiers.iter().filter(|m| self.active.contains(&m.layer)) {
+            let output = overrides.get(&m.layer).copied().unwrap_or(YamlOutput {
+                channel: m.channel,
+                cc: None,
+            });
+            if output != YamlOutput::default() {
+                let channel = output.channel.map_or(channel, |c| c.saturating_sub(1));
+                return (channel, output.cc.unwrap_or(number));
+            }
+        }
+        (channel, number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_active_layer_that_changes_the_output_wins() {
+        let config: YamlConfig = serde_yaml::from_str(
+            r#"
+buttons: []
+modifiers:
+  - { layer: "fx", button: "FX", mode: "Momentary" }
+  - { layer: "shift", button: "SHIFT", mode: "Latching", channel: 2, long_press: true }
+"#,
+        )
+        .unwrap();
+        let knob = layer_outputs(&serde_yaml::from_str(r#"{ fx: { channel: 3 }, shift: "unchanged" }"#).unwrap());
+        let mut layers = Layers::from_yaml(&config);
+        assert_eq!(layers.output(&knob, 0, 0), (0, 0));
+
+        assert_eq!(layers.release("SHIFT"), Some((YamlModifierMode::Latching, true)));
+        assert!(layers.long_press());
+        assert_eq!(layers.output(&knob, 0, 0), (0, 0));
+        assert_eq!(layers.output(&LayerOutputs::new(), 0, 44), (1, 44));
+
+        layers.press("FX");
+        assert_eq!(layers.output(&knob, 0, 0), (2, 0));
+        layers.release_unheld(|_| false);
+        assert_eq!(layers.output(&knob, 0, 0), (0, 0));
+        assert!(layers.is_active("shift"));
+    }
+}
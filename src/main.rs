@@ -1,19 +1,29 @@
-use std::cell::RefCell;
 use std::collections::HashMap;
-use std::io::Read;
+use std::path::Path;
 use std::sync::{Arc, mpsc, Mutex};
 use std::sync::mpsc::Sender;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::AtomicBool;
 use std::thread;
 
+use clap::Parser;
 use rusb::{Context, Device, HotplugBuilder, Registration, UsbContext};
-use system_status_bar_macos::sync_infinite_event_loop;
 
+use crate::capture::{with_capture, Capture, ReplayTransport};
+use crate::cli::{Cli, Command, ProfileCommand};
 use crate::conf::YamlConfig;
-use crate::hid_device::HidDevice;
-use crate::menu_bar::{MenuBar, MenuCommand, set_menu_sender};
+use crate::config_files::ConfigFiles;
+use crate::config_watch::SharedConfig;
+use crate::profiles::Profiles;
+use crate::decoder::ReportFormat;
+use crate::hid_device::{model_name, HidDevice, PRODUCT_ID_MK1, PRODUCT_ID_MK2};
+use crate::hid_hotplug::{DeviceClaim, HidWatcher, HID_POLL_INTERVAL};
+use crate::devices::{DeviceStatus, DeviceStatusMap, PortNames, BASE_PORT_NAME};
+use crate::merged_port::{channel_range, MergedPort, CHANNELS_PER_DEVICE};
+use crate::session::SessionStore;
+use crate::settings::{Backend, Settings};
+use crate::transport::UsbTransport;
 use crate::usb_hotplug::HotPlugHandler;
-use crate::utils::{get_serial_number, get_yaml_file};
+use crate::utils::get_serial_number;
 use crate::x1_process::X1mk1;
 use crate::x1_process_hid::X1mk1Hid;
 
@@ -22,30 +32,209 @@ mod x1_process_hid;
 mod usb_hotplug;
 mod utils;
 mod conf;
+mod config_files;
+mod config_watch;
+mod decoder;
+mod curve;
+mod pickup;
+mod knob_filter;
+mod encoder_output;
+mod gesture;
 mod x1_board;
 mod hid_device;
+mod hid_hotplug;
+#[cfg(all(target_os = "macos", feature = "menu-bar"))]
 mod menu_bar;
+mod transport;
+mod capture;
+mod midi_out;
+mod midi_message;
+mod cli;
+mod settings;
+mod session;
+mod devices;
+mod merged_port;
+mod monitor;
+mod profiles;
+mod validate;
+mod layers;
+#[cfg(test)]
+mod golden_tests;
 
 const USB_ID_VENDOR: u16 = 0x17cc;
 const USB_ID_PRODUCT: u16 = 0x2305;
 
 fn main() {
-    let (sender_menu_bar, receiver_menu_bar) = mpsc::channel::<HashMap<String, bool>>();
+    let cli = Cli::parse();
+    match cli.command {
+        None => run(cli.run.settings()),
+        Some(Command::Run(args)) => run(args.settings()),
+        Some(Command::List(args)) => list_devices(&args.settings()),
+        Some(Command::Monitor(args)) => {
+            let settings = args.settings();
+            let yaml_config = load_config(&settings);
+            let serial = args.serial.as_deref().map(|id| device_serial(&yaml_config, id));
+            if let Err(e) = monitor::monitor(&settings, &yaml_config, serial.as_deref()) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        Some(Command::LedTest(args)) => match args.settings() {
+            Ok(settings) => led_test(settings),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(2);
+            }
+        },
+        Some(Command::Profile(ProfileCommand::List)) => {
+            for line in profiles::describe(&profiles::ProfileState::load()) {
+                println!("{}", line);
+            }
+        }
+        Some(Command::Profile(ProfileCommand::Set { name, serial })) => {
+            let serial = serial.map(|id| device_serial(&load_config(&Settings::default()), &id));
+            if let Err(e) = profiles::select_profile(serial.as_deref(), &name) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            println!("Profile {} selected for {}", name, serial.as_deref().unwrap_or("all controllers"));
+        }
+    }
+}
+
+/// `--clear` and `--once` write a single frame to every device and exit; the other modes keep running.
+fn led_test(settings: Settings) {
+    if settings.backend != Backend::Hid {
+        eprintln!("led-test only supports the hid backend");
+        std::process::exit(2);
+    }
+    let yaml_config = load_config(&settings);
+    if !settings.led_test.clear && !settings.led_test.once {
+        run_headless(settings, yaml_config);
+        return;
+    }
+    let devices = HidDevice::open().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let port_names = PortNames::default();
+    for device in devices {
+        let serial = device.serial_number.clone();
+        let mut x1mk1 = X1mk1Hid::new(
+            Box::new(device.handle),
+            serial.clone(),
+            port_names.name_for(&serial, &yaml_config.devices, settings.port_suffix),
+            yaml_config.clone(),
+            &settings,
+            Arc::new(AtomicBool::new(true)),
+        );
+        if let Err(e) = x1mk1.read() {
+            eprintln!("{}: {}", serial, e);
+        }
+    }
+}
+
+fn run(settings: Settings) {
+    if settings.merged_port && settings.backend == Backend::Libusb {
+        eprintln!("--merged-port needs the HID backend; drop --backend libusb or --merged-port");
+        std::process::exit(2);
+    }
+    let yaml_config = load_config(&settings);
+    if settings.merged_port {
+        let issues = validate::merged_port_issues(&yaml_config);
+        if !issues.is_empty() {
+            eprintln!("--merged-port gives each controller channels 1-{} only:", CHANNELS_PER_DEVICE);
+            for issue in issues {
+                eprintln!("  {}", issue);
+            }
+            std::process::exit(2);
+        }
+    }
+    if let Some(name) = &settings.profile {
+        if let Err(e) = profiles::select_profile(None, name) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+    #[cfg(all(target_os = "macos", feature = "menu-bar"))]
+    run_menu_bar(settings, yaml_config);
+    #[cfg(not(all(target_os = "macos", feature = "menu-bar")))]
+    run_headless(settings, yaml_config);
+}
+
+fn list_devices(settings: &Settings) {
+    let devices = match HidDevice::list() {
+        Ok(devices) => devices,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    if devices.is_empty() {
+        println!("No X1 devices found");
+        return;
+    }
+    // Port names as `run` would assign them when the devices connect in this order.
+    let yaml_config = load_config(settings);
+    let port_names = PortNames::default();
+    for device in devices {
+        let alias = yaml_config.device(&device.id).and_then(|d| d.alias.as_deref()).unwrap_or("-");
+        println!(
+            "{}  port=\"{}\"  id={}  alias={}  serial={}  path={}",
+            model_name(device.product_id),
+            port_names.name_for(&device.id, &yaml_config.devices, settings.port_suffix),
+            device.id,
+            alias,
+            device.serial_number.as_deref().unwrap_or("-"),
+            device.path.to_string_lossy()
+        );
+    }
+}
+
+/// The serial of the `devices:` entry aliased `id`, else `id` itself.
+fn device_serial(yaml_config: &YamlConfig, id: &str) -> String {
+    yaml_config.device(id).map(|d| d.serial.clone()).unwrap_or_else(|| id.to_string())
+}
+
+/// Board mapping from `--config`, or the bundled board.yml with the user's board.yml merged on top.
+/// Exits with the validation report when the mapping is unusable, before any device or MIDI port is opened.
+fn load_config(settings: &Settings) -> YamlConfig {
+    let files = ConfigFiles::resolve(settings.config_path.as_deref()).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    println!("Config: {}", files);
+    files.load().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    })
+}
+
+#[cfg(all(target_os = "macos", feature = "menu-bar"))]
+fn run_menu_bar(settings: Settings, yaml_config: YamlConfig) {
+    use std::cell::RefCell;
+    use std::sync::atomic::Ordering;
+
+    use system_status_bar_macos::sync_infinite_event_loop;
+
+    use crate::menu_bar::{MenuBar, MenuCommand, set_menu_sender};
+
+    let (sender_menu_bar, receiver_menu_bar) = mpsc::channel::<DeviceStatusMap>();
     let (cmd_tx, cmd_rx) = mpsc::channel::<MenuCommand>();
     set_menu_sender(cmd_tx);
 
     let run_flag = Arc::new(AtomicBool::new(true));
-    let last_devices: Arc<Mutex<HashMap<String, bool>>> = Arc::new(Mutex::new(HashMap::new()));
+    let last_devices: Arc<Mutex<DeviceStatusMap>> = Arc::new(Mutex::new(HashMap::new()));
     let last_devices_cmd = Arc::clone(&last_devices);
     let sender_menu_bar_cmd = sender_menu_bar.clone();
     let run_flag_cmd = Arc::clone(&run_flag);
     let run_flag_menu = Arc::clone(&run_flag);
 
+    let icon_path = resolve_menu_icon(settings.menu_icon.as_deref());
     thread::spawn(move || {
-        x1(sender_menu_bar, run_flag_cmd).unwrap();
+        x1(sender_menu_bar, run_flag_cmd, settings, yaml_config).unwrap();
     });
 
-    let icon_path = resolve_menu_icon();
     let menu_bar = RefCell::new(MenuBar::new(icon_path));
 
     thread::spawn(move || {
@@ -68,11 +257,42 @@ fn main() {
     });
 }
 
-fn resolve_menu_icon() -> Option<std::path::PathBuf> {
-    if let Ok(p) = std::env::var("X1_MENU_ICON") {
-        let path = std::path::PathBuf::from(p);
+/// Foreground daemon without any UI: device status goes to stdout, SIGINT/SIGTERM stop the driver.
+fn run_headless(settings: Settings, yaml_config: YamlConfig) {
+    let (sender_status, receiver_status) = mpsc::channel::<DeviceStatusMap>();
+    let (shutdown_tx, shutdown_rx) = mpsc::channel::<()>();
+    ctrlc::set_handler(move || {
+        let _ = shutdown_tx.send(());
+    })
+    .expect("Failed to install signal handler");
+
+    let run_flag = Arc::new(AtomicBool::new(true));
+    thread::spawn(move || {
+        if let Err(e) = x1(sender_status, run_flag, settings, yaml_config) {
+            eprintln!("Driver stopped: {:?}", e);
+        }
+    });
+
+    thread::spawn(move || {
+        while let Ok(devices) = receiver_status.recv() {
+            let mut serials: Vec<(&String, &DeviceStatus)> = devices.iter().collect();
+            serials.sort_by(|a, b| a.0.cmp(b.0));
+            for (serial, status) in serials {
+                println!("{} {} ({})", if status.connected { "🟢" } else { "🔴" }, status.name, serial);
+            }
+        }
+    });
+
+    println!("Easy KONTROL X1 running headless; press Ctrl+C to stop.");
+    let _ = shutdown_rx.recv();
+    println!("Shutting down");
+}
+
+#[cfg(all(target_os = "macos", feature = "menu-bar"))]
+fn resolve_menu_icon(configured: Option<&Path>) -> Option<std::path::PathBuf> {
+    if let Some(path) = configured {
         if path.exists() {
-            return Some(path);
+            return Some(path.to_path_buf());
         }
     }
     let local = std::path::PathBuf::from("logo/18x18.png");
@@ -91,60 +311,76 @@ fn resolve_menu_icon() -> Option<std::path::PathBuf> {
     }
 }
 
-fn x1(sender_menu_bar: Sender<HashMap<String, bool>>, run_flag: Arc<AtomicBool>) -> rusb::Result<()> {
-    let mut file = get_yaml_file();
-    let mut yaml_content = String::new();
-    file.read_to_string(&mut yaml_content).expect("Failed to read YAML file");
-    let yaml_config: YamlConfig = serde_yaml::from_str(&yaml_content).expect("Failed to parse YAML");
-    let yaml_config = Arc::new(yaml_config);
+fn x1(
+    sender_menu_bar: Sender<DeviceStatusMap>,
+    run_flag: Arc<AtomicBool>,
+    settings: Settings,
+    yaml_config: YamlConfig,
+) -> rusb::Result<()> {
+    let settings = Arc::new(settings);
+    let port_names = Arc::new(PortNames::default());
 
-    let force_libusb = std::env::var("FORCE_LIBUSB").ok().as_deref() == Some("1");
-    // Try HID API first (works better on macOS 26.1+) unless forced to libusb
-    if !force_libusb {
+    if let Some(replay_path) = &settings.replay {
+        replay(replay_path, &sender_menu_bar, &yaml_config, &settings, &run_flag);
+        loop {
+            std::thread::sleep(std::time::Duration::from_secs(1));
+        }
+    }
+
+    let shared_config = Arc::new(SharedConfig::new(yaml_config));
+    config_watch::watch(settings.config_path.clone(), Arc::clone(&shared_config));
+    let profiles = Profiles::start(shared_config);
+
+    // Try HID API first (works better on macOS 26.1+) unless libusb was requested
+    if settings.backend == Backend::Hid {
         println!("Attempting to use HID API...");
-        if let Ok(hid_devices) = HidDevice::open() {
-            println!("✓ Successfully opened {} device(s) via HID", hid_devices.len());
-            for hid_dev in hid_devices {
-                let serial = hid_dev.serial_number.clone();
-                let devices_map = Arc::new(Mutex::new(HashMap::new()));
-                devices_map.lock().unwrap().insert(serial.clone(), true);
-                sender_menu_bar.send(devices_map.lock().unwrap().clone()).unwrap();
-                
-                let sender_mb = sender_menu_bar.clone();
-                let devices_thread = Arc::clone(&devices_map);
-                let serial_clone = serial.clone();
-                let yaml_config_clone = Arc::clone(&yaml_config);
-                let run_flag = Arc::clone(&run_flag);
-                
-                thread::spawn(move || {
-                    let mut x1mk1 = X1mk1Hid::new(
-                        hid_dev.handle,
-                        serial_clone.clone(),
-                        (*yaml_config_clone).clone(),
-                        run_flag,
-                    );
-                    loop {
-                        match x1mk1.read() {
-                            Ok(_) => {}
-                            Err(_e) => {
-                                devices_thread.lock().unwrap().insert(serial_clone.clone(), false);
-                                sender_mb.send(devices_thread.lock().unwrap().clone()).unwrap();
-                                break;
+        match HidWatcher::new() {
+            Ok(mut watcher) => {
+                let hid_devices = watcher.poll();
+                // An X1 that libusb can see but hidapi cannot needs the libusb backend.
+                if !hid_devices.is_empty() || !usb_x1_present() {
+                    println!("✓ Opened {} device(s) via HID; watching for hotplug", hid_devices.len());
+                    let merged_port = if settings.merged_port {
+                        match MergedPort::open(BASE_PORT_NAME) {
+                            Ok(port) => Some(Arc::new(port)),
+                            Err(e) => {
+                                eprintln!("Failed to create merged MIDI port: {}", e);
+                                std::process::exit(1);
                             }
                         }
+                    } else {
+                        None
+                    };
+                    let ctx = HidContext {
+                        devices: Arc::new(Mutex::new(HashMap::new())),
+                        sessions: Arc::new(SessionStore::default()),
+                        port_names: Arc::clone(&port_names),
+                        merged_port,
+                        sender_menu_bar: sender_menu_bar.clone(),
+                        profiles: Arc::clone(&profiles),
+                        settings: Arc::clone(&settings),
+                        run_flag: Arc::clone(&run_flag),
+                    };
+                    let mut arrived = hid_devices;
+                    loop {
+                        for (hid_dev, claim) in arrived {
+                            spawn_hid_handler(hid_dev, claim, ctx.clone());
+                        }
+                        thread::sleep(HID_POLL_INTERVAL);
+                        arrived = watcher.poll();
                     }
-                });
-            }
-            // Keep running the event loop
-            loop {
-                std::thread::sleep(std::time::Duration::from_secs(1));
+                }
             }
+            Err(e) => eprintln!("{}", e),
         }
     } else {
-        println!("FORCE_LIBUSB=1 set; skipping HID API.");
+        println!("--backend libusb set; skipping HID API.");
+    }
+    if settings.merged_port {
+        eprintln!("--merged-port needs the HID backend, but no HID device was found; using one port per device.");
     }
 
-    // Fall back to libusb if HID doesn't find devices
+    // Fall back to libusb if HID can't reach the device
     println!("HID API did not find devices, falling back to libusb...");
     if rusb::has_hotplug() {
         println!("libusb hotplug supported");
@@ -181,28 +417,40 @@ fn x1(sender_menu_bar: Sender<HashMap<String, bool>>, run_flag: Arc<AtomicBool>)
                             match device.open() {
                                 Ok(handle) => {
                                     println!("Opening device (pid=0x{:04x})", descriptor.product_id());
-                                    devices_map.lock().unwrap().insert(serial_for_map.clone(), true);
-                                    sender_menu_bar.send(devices_map.lock().unwrap().clone()).unwrap();
+                                    let (follower, yaml_config) = profiles.follow(&serial);
+                                    let device_config = profiles.device(&serial);
+                                    let port_name = port_names.name_for(&serial, &profiles.devices(), settings.port_suffix);
+                                    report_status(&devices_map, &sender_menu_bar, &serial_for_map, &port_name, true);
                                     let devices_thread = Arc::clone(&devices_map);
                                     let sender_mb = sender_menu_bar.clone();
                                     let device_clone = device.clone();
-                                    let yaml_config_clone = Arc::clone(&yaml_config);
                                     let run_flag = Arc::clone(&run_flag);
+                                    let settings = Arc::clone(&settings);
                                     thread::spawn(move || {
+                                        let transport = with_capture(
+                                            Box::new(UsbTransport::new(device_clone, handle, settings.led_debug)),
+                                            &serial_for_x1,
+                                            ReportFormat::Usb,
+                                            settings.capture_dir.as_deref(),
+                                        );
                                         let mut x1mk1 = X1mk1::new(
-                                            device_clone,
-                                            handle,
+                                            transport,
                                             serial_for_x1,
-                                            (*yaml_config_clone).clone(),
+                                            port_name.clone(),
+                                            (*yaml_config).clone(),
+                                            &settings,
                                             run_flag,
                                         );
+                                        x1mk1.follow_config(follower);
+                                        if let Some(device_config) = &device_config {
+                                            x1mk1.use_device_config(device_config);
+                                        }
                                         loop {
                                             match x1mk1.read() {
                                                 Ok(_) => {}
                                                 Err(e) => {
                                                     eprintln!("Error reading from device: {:?}", e);
-                                                    devices_thread.lock().unwrap().insert(serial_for_map.clone(), false);
-                                                    sender_mb.send(devices_thread.lock().unwrap().clone()).unwrap();
+                                                    report_status(&devices_thread, &sender_mb, &serial_for_map, &port_name, false);
                                                     break;
                                                 }
                                             }
@@ -221,34 +469,49 @@ fn x1(sender_menu_bar: Sender<HashMap<String, bool>>, run_flag: Arc<AtomicBool>)
         thread::spawn({
             let devices = Arc::clone(&devices);
             let sender_menu_bar = sender_menu_bar.clone();
+            let settings = Arc::clone(&settings);
+            let port_names = Arc::clone(&port_names);
+            let profiles = Arc::clone(&profiles);
             move || loop {
                 let device = rx.recv().unwrap();
             println!("Device received on channel");
                 let handle = device.open().unwrap();
                 let serial_number = get_serial_number(&device);
                 let serial_number_clone = serial_number.clone();
-                let yaml_config: YamlConfig = serde_yaml::from_str(&yaml_content).expect("Failed to parse YAML");
-                devices.lock().unwrap().insert(serial_number_clone.clone(), true);
-                sender_menu_bar.send(devices.lock().unwrap().clone()).unwrap();
+                let (follower, yaml_config) = profiles.follow(&serial_number);
+                let device_config = profiles.device(&serial_number);
+                let port_name = port_names.name_for(&serial_number, &profiles.devices(), settings.port_suffix);
+                report_status(&devices, &sender_menu_bar, &serial_number, &port_name, true);
                 let run_flag = Arc::clone(&run_flag);
                 thread::spawn({
                     let devices = Arc::clone(&devices);
                     let sender_menu_bar = sender_menu_bar.clone();
+                    let settings = Arc::clone(&settings);
                     move || {
+                        let transport = with_capture(
+                            Box::new(UsbTransport::new(device, handle, settings.led_debug)),
+                            &serial_number,
+                            ReportFormat::Usb,
+                            settings.capture_dir.as_deref(),
+                        );
                         let mut x1mk1 = X1mk1::new(
-                            device,
-                            handle,
+                            transport,
                             serial_number,
-                            yaml_config.clone(),
+                            port_name.clone(),
+                            (*yaml_config).clone(),
+                            &settings,
                             run_flag,
                         );
+                        x1mk1.follow_config(follower);
+                        if let Some(device_config) = &device_config {
+                            x1mk1.use_device_config(device_config);
+                        }
                         loop {
                             match x1mk1.read() {
                                 Ok(x) => x,
                                 Err(e) => {
                                     eprintln!("Error reading from device: {:?}", e);
-                                    devices.lock().unwrap().insert(serial_number_clone, false);
-                                    sender_menu_bar.send(devices.lock().unwrap().clone()).unwrap();
+                                    report_status(&devices, &sender_menu_bar, &serial_number_clone, &port_name, false);
                                     break;
                                 }
                             };
@@ -285,28 +548,40 @@ fn x1(sender_menu_bar: Sender<HashMap<String, bool>>, run_flag: Arc<AtomicBool>)
                                     match device.open() {
                                         Ok(handle) => {
                                                 println!("Polling: spawning handler");
-                                            let yaml_config: YamlConfig = serde_yaml::from_str(&yaml_content).expect("Failed to parse YAML");
-                                            devices.lock().unwrap().insert(serial_clone.clone(), true);
-                                            sender_menu_bar.send(devices.lock().unwrap().clone()).unwrap();
+                                            let (follower, yaml_config) = profiles.follow(&serial_number);
+                                            let device_config = profiles.device(&serial_number);
+                                            let port_name = port_names.name_for(&serial_number, &profiles.devices(), settings.port_suffix);
+                                            report_status(&devices, &sender_menu_bar, &serial_clone, &port_name, true);
                                             let devices_thread = Arc::clone(&devices);
                                             let sender_mb = sender_menu_bar.clone();
                                             let device_clone = device.clone();
                                             let run_flag = Arc::clone(&run_flag);
+                                            let settings = Arc::clone(&settings);
                                             thread::spawn(move || {
+                                                let transport = with_capture(
+                                                    Box::new(UsbTransport::new(device_clone, handle, settings.led_debug)),
+                                                    &serial_number,
+                                                    ReportFormat::Usb,
+                                                    settings.capture_dir.as_deref(),
+                                                );
                                                 let mut x1mk1 = X1mk1::new(
-                                                    device_clone,
-                                                    handle,
+                                                    transport,
                                                     serial_number,
-                                                    yaml_config.clone(),
+                                                    port_name.clone(),
+                                                    (*yaml_config).clone(),
+                                                    &settings,
                                                     run_flag,
                                                 );
+                                                x1mk1.follow_config(follower);
+                                                if let Some(device_config) = &device_config {
+                                                    x1mk1.use_device_config(device_config);
+                                                }
                                                 loop {
                                                     match x1mk1.read() {
                                                         Ok(_) => {}
                                                         Err(e) => {
                                                             eprintln!("Error reading from device: {:?}", e);
-                                                            devices_thread.lock().unwrap().insert(serial_clone.clone(), false);
-                                                            sender_mb.send(devices_thread.lock().unwrap().clone()).unwrap();
+                                                            report_status(&devices_thread, &sender_mb, &serial_clone, &port_name, false);
                                                             break;
                                                         }
                                                     }
@@ -325,5 +600,156 @@ fn x1(sender_menu_bar: Sender<HashMap<String, bool>>, run_flag: Arc<AtomicBool>)
             std::thread::sleep(std::time::Duration::from_secs(1));
         }
     }
-    Ok(())
+}
+
+/// Everything the HID device handlers share.
+#[derive(Clone)]
+struct HidContext {
+    devices: Arc<Mutex<DeviceStatusMap>>,
+    sessions: Arc<SessionStore>,
+    port_names: Arc<PortNames>,
+    merged_port: Option<Arc<MergedPort>>,
+    sender_menu_bar: Sender<DeviceStatusMap>,
+    profiles: Arc<Profiles>,
+    settings: Arc<Settings>,
+    run_flag: Arc<AtomicBool>,
+}
+
+/// Run one HID device until it disconnects; dropping `claim` afterwards lets the watcher reopen it.
+/// The handler's state is kept in `ctx.sessions` and restored when the same device comes back.
+fn spawn_hid_handler(hid_dev: HidDevice, claim: DeviceClaim, ctx: HidContext) {
+    let serial = hid_dev.serial_number.clone();
+    let (follower, yaml_config) = ctx.profiles.follow(&serial);
+    let device_config = ctx.profiles.device(&serial);
+    let merged_slot = match &ctx.merged_port {
+        Some(port) => match port.slot_for(&serial) {
+            Ok(slot) => Some((Arc::clone(port), slot)),
+            Err(e) => {
+                eprintln!("{}: {}", serial, e);
+                return;
+            }
+        },
+        None => None,
+    };
+    let is_merged = merged_slot.is_some();
+    let port_name = match &merged_slot {
+        Some((_, slot)) => format!("{} ({})", BASE_PORT_NAME, channel_range(*slot)),
+        None => ctx.port_names.name_for(&serial, &ctx.profiles.devices(), ctx.settings.port_suffix),
+    };
+    report_status(&ctx.devices, &ctx.sender_menu_bar, &serial, &port_name, true);
+    thread::spawn(move || {
+        let _claim = claim;
+        let settings = &ctx.settings;
+        let transport = with_capture(
+            Box::new(hid_dev.handle),
+            &serial,
+            ReportFormat::Hid,
+            settings.capture_dir.as_deref(),
+        );
+        let mut x1mk1 = match merged_slot {
+            Some((port, slot)) => {
+                let mut x1mk1 = X1mk1Hid::with_output(
+                    transport,
+                    Box::new(port.sink(slot)),
+                    serial.clone(),
+                    port_name.clone(),
+                    (*yaml_config).clone(),
+                    settings,
+                    Arc::clone(&ctx.run_flag),
+                );
+                x1mk1.use_merged_input(port, slot);
+                x1mk1
+            }
+            None => X1mk1Hid::new(
+                transport,
+                serial.clone(),
+                port_name.clone(),
+                (*yaml_config).clone(),
+                settings,
+                Arc::clone(&ctx.run_flag),
+            ),
+        };
+        x1mk1.follow_config(follower);
+        if let Some(mut device_config) = device_config {
+            if is_merged && device_config.channel_offset.take().is_some() {
+                eprintln!("{}: channel_offset is ignored with --merged-port", serial);
+            }
+            x1mk1.use_device_config(&device_config);
+        }
+        if let Some(state) = ctx.sessions.get(&serial) {
+            println!("Restoring session state for {}", serial);
+            x1mk1.restore(state);
+        }
+        loop {
+            match x1mk1.read() {
+                Ok(_) => {}
+                Err(e) => {
+                    eprintln!("Error reading from {}: {}", serial, e);
+                    ctx.sessions.save(&serial, x1mk1.snapshot());
+                    report_status(&ctx.devices, &ctx.sender_menu_bar, &serial, &port_name, false);
+                    break;
+                }
+            }
+        }
+    });
+}
+
+/// Record a device's connection state and push the whole map to the UI.
+fn report_status(
+    devices: &Mutex<DeviceStatusMap>,
+    sender_menu_bar: &Sender<DeviceStatusMap>,
+    serial: &str,
+    name: &str,
+    connected: bool,
+) {
+    let mut devices = devices.lock().unwrap();
+    devices.insert(serial.to_string(), DeviceStatus { name: name.to_string(), connected });
+    let _ = sender_menu_bar.send(devices.clone());
+}
+
+/// True when libusb sees an X1, used to decide whether hidapi missing it means "not plugged in".
+fn usb_x1_present() -> bool {
+    let Ok(context) = Context::new() else { return false };
+    let Ok(list) = context.devices() else { return false };
+    list.iter().any(|device| {
+        device
+            .device_descriptor()
+            .map(|d| d.vendor_id() == USB_ID_VENDOR && matches!(d.product_id(), PRODUCT_ID_MK1 | PRODUCT_ID_MK2))
+            .unwrap_or(false)
+    })
+}
+
+/// Feed a capture file (see `--capture`) through the matching device handler as if the controller were attached.
+fn replay(
+    path: &Path,
+    sender_menu_bar: &Sender<DeviceStatusMap>,
+    yaml_config: &YamlConfig,
+    settings: &Settings,
+    run_flag: &Arc<AtomicBool>,
+) {
+    let capture = match Capture::load(path) {
+        Ok(capture) => capture,
+        Err(e) => {
+            eprintln!("Failed to load capture {}: {}", path.display(), e);
+            return;
+        }
+    };
+    println!("Replaying {} report(s) from {} (serial {})", capture.records.len(), path.display(), capture.serial);
+    let serial = capture.serial.clone();
+    let port_name = PortNames::default().name_for(&serial, &yaml_config.devices, settings.port_suffix);
+    let devices = Mutex::new(DeviceStatusMap::new());
+    report_status(&devices, sender_menu_bar, &serial, &port_name, true);
+
+    let format = capture.format;
+    let transport = Box::new(ReplayTransport::new(capture));
+    let result = match format {
+        ReportFormat::Hid => {
+            X1mk1Hid::new(transport, serial.clone(), port_name.clone(), yaml_config.clone(), settings, Arc::clone(run_flag)).read()
+        }
+        ReportFormat::Usb => {
+            X1mk1::new(transport, serial.clone(), port_name.clone(), yaml_config.clone(), settings, Arc::clone(run_flag)).read()
+        }
+    };
+    println!("Replay finished: {:?}", result);
+    report_status(&devices, sender_menu_bar, &serial, &port_name, false);
 }
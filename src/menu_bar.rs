@@ -1,4 +1,3 @@
-use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::mpsc::Sender;
 use std::sync::OnceLock;
@@ -10,6 +9,8 @@ use objc::declare::ClassDecl;
 use objc::runtime::{Class, Sel};
 use objc::{class, msg_send, sel, sel_impl};
 
+use crate::devices::{DeviceStatus, DeviceStatusMap};
+
 pub struct MenuBar {
     status_item: id,
     menu: id,
@@ -55,7 +56,7 @@ impl MenuBar {
         }
     }
 
-    pub fn update_menu(&mut self, items: &HashMap<String, bool>, running: bool) {
+    pub fn update_menu(&mut self, items: &DeviceStatusMap, running: bool) {
         unsafe {
             // Clear existing items.
             let count: usize = msg_send![self.menu, numberOfItems];
@@ -71,15 +72,11 @@ impl MenuBar {
             let _: () = msg_send![self.menu, addItem: stop_item];
             let _: () = msg_send![self.menu, addItem: NSMenuItem::separatorItem(nil)];
 
-            let mut names: Vec<(&String, &bool)> = items.iter().collect();
-            names.sort_by(|a, b| a.0.cmp(b.0));
-            for (idx, (_name, connected)) in names.iter().enumerate() {
-                let prefix = if **connected { "🟢" } else { "🔴" };
-                let label = if names.len() > 1 {
-                    format!("EASY KONTROL X1 {}", idx + 1)
-                } else {
-                    "EASY KONTROL X1".to_string()
-                };
+            let mut names: Vec<(&String, &DeviceStatus)> = items.iter().collect();
+            names.sort_by(|a, b| a.1.name.cmp(&b.1.name));
+            for (_serial, status) in names {
+                let prefix = if status.connected { "🟢" } else { "🔴" };
+                let label = &status.name;
                 let title = NSString::alloc(nil).init_str(&format!("{} {}", prefix, label));
                 let item = NSMenuItem::alloc(nil)
                     .initWithTitle_action_keyEquivalent_(title, sel!(noop:), NSString::alloc(nil).init_str(""));
@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+
+use midir::os::unix::{VirtualInput, VirtualOutput};
+use midir::{MidiInput, MidiInputConnection, MidiOutput, MidiOutputConnection};
+
+use crate::midi_out::ChannelOffsetSink;
+
+/// MIDI channels each controller occupies: deck/normal, SHIFT and LED/FX-hold.
+pub const CHANNELS_PER_DEVICE: u8 = 3;
+/// How many controllers fit on the 16 channels of one port.
+pub const MAX_DEVICES: u8 = 16 / CHANNELS_PER_DEVICE;
+
+/// One virtual port pair shared by every controller. Device `n` (0-based) has its messages
+/// moved up by `3 * n` channels, and incoming messages are handed to the device whose
+/// channel range they fall in, moved back down to channels 1-3.
+pub struct MergedPort {
+    out: Arc<Mutex<MidiOutputConnection>>,
+    routes: Arc<Mutex<HashMap<u8, Sender<Vec<u8>>>>>,
+    slots: Mutex<Slots>,
+    // Mutex only to make the port shareable between handler threads; never locked.
+    _midi_conn_in: Mutex<MidiInputConnection<()>>,
+}
+
+impl MergedPort {
+    pub fn open(port_name: &str) -> Result<Self, String> {
+        let midi_out = MidiOutput::new("Easy KONTROL X1").map_err(|e| e.to_string())?;
+        let out = midi_out.create_virtual(port_name).map_err(|e| e.to_string())?;
+        let routes: Arc<Mutex<HashMap<u8, Sender<Vec<u8>>>>> = Arc::new(Mutex::new(HashMap::new()));
+        let routes_in = Arc::clone(&routes);
+        let midi_in = MidiInput::new("Easy KONTROL X1").map_err(|e| e.to_string())?;
+        let midi_conn_in = midi_in
+            .create_virtual(
+                port_name,
+                move |_stamp, message: &[u8], _| {
+                    if let Some((slot, local)) = split_channel(message) {
+                        if let Some(sender) = routes_in.lock().unwrap().get(&slot) {
+                            let _ = sender.send(local);
+                        }
+                    }
+                },
+                (),
+            )
+            .map_err(|e| e.to_string())?;
+        println!("✓ Created merged virtual MIDI ports: {}", port_name);
+        Ok(Self {
+            out: Arc::new(Mutex::new(out)),
+            routes,
+            slots: Mutex::new(Slots::default()),
+            _midi_conn_in: Mutex::new(midi_conn_in),
+        })
+    }
+
+    /// Channel slot of a device; a device keeps its slot across reconnects.
+    pub fn slot_for(&self, serial: &str) -> Result<u8, String> {
+        self.slots.lock().unwrap().slot_for(serial)
+    }
+
+    /// Output for the device in `slot`.
+    pub fn sink(&self, slot: u8) -> ChannelOffsetSink {
+        ChannelOffsetSink::within(Box::new(Arc::clone(&self.out)), slot * CHANNELS_PER_DEVICE, CHANNELS_PER_DEVICE)
+    }
+
+    /// Deliver incoming messages for `slot` to `sender`, replacing any previous handler of that slot.
+    pub fn route(&self, slot: u8, sender: Sender<Vec<u8>>) {
+        self.routes.lock().unwrap().insert(slot, sender);
+    }
+}
+
+/// Channel slots handed out so far, by serial.
+#[derive(Default)]
+struct Slots(HashMap<String, u8>);
+
+impl Slots {
+    fn slot_for(&mut self, serial: &str) -> Result<u8, String> {
+        if let Some(&slot) = self.0.get(serial) {
+            return Ok(slot);
+        }
+        let slot = (0..MAX_DEVICES)
+            .find(|s| !self.0.values().any(|v| v == s))
+            .ok_or_else(|| format!("Merged port is full ({} controllers)", MAX_DEVICES))?;
+        self.0.insert(serial.to_string(), slot);
+        Ok(slot)
+    }
+}
+
+/// Human-readable channel range of `slot`, e.g. "ch 4-6".
+pub fn channel_range(slot: u8) -> String {
+    let first = slot * CHANNELS_PER_DEVICE + 1;
+    format!("ch {}-{}", first, first + CHANNELS_PER_DEVICE - 1)
+}
+
+/// Split an incoming channel message into the owning slot and the message on that device's own channels.
+fn split_channel(message: &[u8]) -> Option<(u8, Vec<u8>)> {
+    let status = *message.first()?;
+    if !(0x80..0xF0).contains(&status) {
+        return None;
+    }
+    let channel = status & 0x0F;
+    let slot = channel / CHANNELS_PER_DEVICE;
+    if slot >= MAX_DEVICES {
+        return None;
+    }
+    let mut local = message.to_vec();
+    local[0] = status - slot * CHANNELS_PER_DEVICE;
+    Some((slot, local))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::midi_out::{MidiSink, RecordingSink};
+
+    #[test]
+    fn slots_are_kept_per_serial_until_full() {
+        let mut slots = Slots::default();
+        assert_eq!(slots.slot_for("A"), Ok(0));
+        assert_eq!(slots.slot_for("B"), Ok(1));
+        assert_eq!(slots.slot_for("A"), Ok(0));
+        for n in 2..MAX_DEVICES {
+            assert_eq!(slots.slot_for(&n.to_string()), Ok(n));
+        }
+        assert!(slots.slot_for("late").is_err());
+        assert_eq!(channel_range(1), "ch 4-6");
+    }
+
+    #[test]
+    fn each_slot_owns_three_channels_both_ways() {
+        // Slot 1: channels 4-6.
+        let recorded = RecordingSink::new();
+        let mut out = ChannelOffsetSink::within(Box::new(recorded.clone()), CHANNELS_PER_DEVICE, CHANNELS_PER_DEVICE);
+        out.send_all(vec![vec![0xB0, 1, 127], vec![0xB2, 1, 127], vec![0xB3, 1, 127]]);
+        assert_eq!(recorded.take(), vec![vec![0xB3, 1, 127], vec![0xB5, 1, 127]]);
+
+        assert_eq!(split_channel(&[0xB5, 1, 127]), Some((1, vec![0xB2, 1, 127])));
+        assert_eq!(split_channel(&[0x90, 60, 1]), Some((0, vec![0x90, 60, 1])));
+        assert_eq!(split_channel(&[0xBF, 1, 1]), None);
+        assert_eq!(split_channel(&[0xF8]), None);
+    }
+}
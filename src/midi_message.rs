@@ -0,0 +1,102 @@
+use crate::conf::{YamlButton, YamlMessageType};
+
+const NRPN_PARAM_MSB: u8 = 0x63;
+const NRPN_PARAM_LSB: u8 = 0x62;
+const DATA_ENTRY_MSB: u8 = 0x06;
+const DATA_ENTRY_LSB: u8 = 0x26;
+/// Offset from a CC 0-31 to the CC carrying its LSB.
+pub const CC_LSB_OFFSET: u8 = 32;
+
+/// How a control's values are turned into MIDI messages.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Message {
+    pub kind: YamlMessageType,
+    /// 0-based channel from the config; `None` leaves it to the handler.
+    pub channel: Option<u8>,
+    pub nrpn_msb: u8,
+    /// 14-bit output for knobs.
+    pub high_res: bool,
+}
+
+impl Message {
+    pub fn from_yaml(yaml_button: &YamlButton) -> Self {
+        Self {
+            kind: yaml_button.message.unwrap_or_default(),
+            channel: yaml_button.channel.map(|c| c.saturating_sub(1)),
+            nrpn_msb: yaml_button.nrpn_msb.unwrap_or(0),
+            high_res: yaml_button.high_res.unwrap_or(false),
+        }
+    }
+
+    /// Messages for a knob or encoder value (0-127) on `channel` (0-15) and `number`.
+    pub fn value(&self, channel: u8, number: u8, value: u8) -> Vec<Vec<u8>> {
+        let value = value & 0x7F;
+        match self.kind {
+            YamlMessageType::Cc => vec![vec![0xB0 | channel, number, value]],
+            YamlMessageType::Note if value == 0 => vec![vec![0x80 | channel, number, 0]],
+            YamlMessageType::Note => vec![vec![0x90 | channel, number, value]],
+            YamlMessageType::ProgramChange => vec![vec![0xC0 | channel, value]],
+            YamlMessageType::PitchBend => {
+                // Repeat the 7 bits in the LSB so 0 and 127 reach both ends of the range.
+                let bend = (value as u16) << 7 | value as u16;
+                vec![vec![0xE0 | channel, (bend & 0x7F) as u8, (bend >> 7) as u8]]
+            }
+            YamlMessageType::Nrpn => vec![
+                vec![0xB0 | channel, NRPN_PARAM_MSB, self.nrpn_msb],
+                vec![0xB0 | channel, NRPN_PARAM_LSB, number],
+                vec![0xB0 | channel, DATA_ENTRY_MSB, value],
+            ],
+        }
+    }
+
+    /// Messages for a 14-bit value (0-16383). Types without a 14-bit form get the top 7 bits.
+    pub fn value14(&self, channel: u8, number: u8, value: u16) -> Vec<Vec<u8>> {
+        let (msb, lsb) = ((value >> 7) as u8 & 0x7F, value as u8 & 0x7F);
+        match self.kind {
+            YamlMessageType::Cc => vec![
+                vec![0xB0 | channel, number, msb],
+                vec![0xB0 | channel, number.wrapping_add(CC_LSB_OFFSET) & 0x7F, lsb],
+            ],
+            YamlMessageType::PitchBend => vec![vec![0xE0 | channel, lsb, msb]],
+            YamlMessageType::Nrpn => vec![
+                vec![0xB0 | channel, NRPN_PARAM_MSB, self.nrpn_msb],
+                vec![0xB0 | channel, NRPN_PARAM_LSB, number],
+                vec![0xB0 | channel, DATA_ENTRY_MSB, msb],
+                vec![0xB0 | channel, DATA_ENTRY_LSB, lsb],
+            ],
+            YamlMessageType::Note | YamlMessageType::ProgramChange => self.value(channel, number, msb),
+        }
+    }
+
+    /// Messages for a button going down (`pressed`) or up.
+    pub fn button(&self, channel: u8, number: u8, pressed: bool) -> Vec<Vec<u8>> {
+        match self.kind {
+            YamlMessageType::ProgramChange if pressed => vec![vec![0xC0 | channel, number]],
+            YamlMessageType::ProgramChange => Vec::new(),
+            _ => self.value(channel, number, if pressed { 127 } else { 0 }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_every_message_type() {
+        let message = |kind| Message { kind, channel: None, nrpn_msb: 2, high_res: false };
+        assert_eq!(message(YamlMessageType::Cc).value(1, 20, 64), vec![vec![0xB1, 20, 64]]);
+        assert_eq!(message(YamlMessageType::Note).button(0, 40, true), vec![vec![0x90, 40, 127]]);
+        assert_eq!(message(YamlMessageType::Note).button(0, 40, false), vec![vec![0x80, 40, 0]]);
+        assert_eq!(message(YamlMessageType::ProgramChange).button(3, 5, true), vec![vec![0xC3, 5]]);
+        assert!(message(YamlMessageType::ProgramChange).button(3, 5, false).is_empty());
+        assert_eq!(message(YamlMessageType::PitchBend).value(0, 0, 127), vec![vec![0xE0, 0x7F, 0x7F]]);
+        assert_eq!(message(YamlMessageType::PitchBend).value(0, 0, 0), vec![vec![0xE0, 0, 0]]);
+        assert_eq!(
+            message(YamlMessageType::Nrpn).value(0, 9, 100),
+            vec![vec![0xB0, 0x63, 2], vec![0xB0, 0x62, 9], vec![0xB0, 0x06, 100]]
+        );
+        assert_eq!(message(YamlMessageType::Cc).value14(0, 1, 0x3FFF), vec![vec![0xB0, 1, 0x7F], vec![0xB0, 33, 0x7F]]);
+        assert_eq!(message(YamlMessageType::PitchBend).value14(2, 0, 0x2000), vec![vec![0xE2, 0, 0x40]]);
+    }
+}
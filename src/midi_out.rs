@@ -0,0 +1,127 @@
+use std::sync::{Arc, Mutex};
+
+use midir::MidiOutputConnection;
+
+/// Destination for the MIDI messages a device handler emits.
+pub trait MidiSink: Send {
+    fn send(&mut self, message: &[u8]) -> Result<(), String>;
+
+    /// Send `messages` in order; like the handlers' single sends, failures are not reported.
+    fn send_all(&mut self, messages: Vec<Vec<u8>>) {
+        for message in messages {
+            let _ = self.send(&message);
+        }
+    }
+}
+
+impl MidiSink for MidiOutputConnection {
+    fn send(&mut self, message: &[u8]) -> Result<(), String> {
+        MidiOutputConnection::send(self, message).map_err(|e| e.to_string())
+    }
+}
+
+/// An output port shared between several handlers.
+impl MidiSink for Arc<Mutex<MidiOutputConnection>> {
+    fn send(&mut self, message: &[u8]) -> Result<(), String> {
+        self.lock().unwrap().send(message).map_err(|e| e.to_string())
+    }
+}
+
+/// [`MidiSink`] wrapper moving every channel message up by `offset` channels.
+pub struct ChannelOffsetSink {
+    inner: Box<dyn MidiSink>,
+    pub offset: u8,
+    /// Channels the handler may use before the offset; messages above are dropped.
+    channels: u8,
+    /// A dropped message was reported already.
+    dropped: bool,
+}
+
+impl ChannelOffsetSink {
+    pub fn new(inner: Box<dyn MidiSink>, offset: u8) -> Self {
+        Self::within(inner, offset, 16)
+    }
+
+    /// Like [`Self::new`], but only channels 1 to `channels` go through.
+    pub fn within(inner: Box<dyn MidiSink>, offset: u8, channels: u8) -> Self {
+        Self { inner, offset, channels, dropped: false }
+    }
+}
+
+impl MidiSink for ChannelOffsetSink {
+    fn send(&mut self, message: &[u8]) -> Result<(), String> {
+        let mut shifted = message.to_vec();
+        if let Some(status) = shifted.first_mut() {
+            if (0x80..0xF0).contains(status) {
+                let local = *status & 0x0F;
+                let channel = local + self.offset;
+                if local >= self.channels || channel > 0x0F {
+                    let error = format!("channel {} out of range", local + 1);
+                    if !self.dropped {
+                        eprintln!("Dropping MIDI on {} (and any later ones)", error);
+                        self.dropped = true;
+                    }
+                    return Err(error);
+                }
+                *status = (*status & 0xF0) | channel;
+            }
+        }
+        self.inner.send(&shifted)
+    }
+}
+
+/// Undo a channel offset on an incoming message; `None` when it is on a channel below the offset.
+pub fn shift_channel_down(message: &[u8], offset: u8) -> Option<Vec<u8>> {
+    let mut local = message.to_vec();
+    if let Some(status) = local.first_mut() {
+        if (0x80..0xF0).contains(status) {
+            let channel = (*status & 0x0F).checked_sub(offset)?;
+            *status = (*status & 0xF0) | channel;
+        }
+    }
+    Some(local)
+}
+
+/// Sink that keeps every message in memory; clones share the same buffer.
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub struct RecordingSink {
+    messages: Arc<Mutex<Vec<Vec<u8>>>>,
+}
+
+#[cfg(test)]
+impl RecordingSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remove and return everything sent since the last call.
+    pub fn take(&self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut *self.messages.lock().unwrap())
+    }
+}
+
+#[cfg(test)]
+impl MidiSink for RecordingSink {
+    fn send(&mut self, message: &[u8]) -> Result<(), String> {
+        self.messages.lock().unwrap().push(message.to_vec());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offset_moves_channels_up_and_back_down() {
+        let recorded = RecordingSink::new();
+        let mut out = ChannelOffsetSink::new(Box::new(recorded.clone()), 4);
+        out.send_all(vec![vec![0xB0, 7, 1], vec![0xBC, 7, 1], vec![0xF8]]);
+        assert_eq!(recorded.take(), vec![vec![0xB4, 7, 1], vec![0xF8]]);
+
+        assert_eq!(shift_channel_down(&[0x94, 60, 1], 4), Some(vec![0x90, 60, 1]));
+        assert_eq!(shift_channel_down(&[0x93, 60, 1], 4), None);
+        assert_eq!(shift_channel_down(&[0xF8], 4), Some(vec![0xF8]));
+    }
+}
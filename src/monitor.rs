@@ -0,0 +1,75 @@
+use std::time::Instant;
+
+use rusb::{Context, UsbContext};
+
+use crate::conf::YamlConfig;
+use crate::decoder::{hid_payload, ReportDecoder, ReportFormat};
+use crate::hid_device::{HidDevice, PRODUCT_ID_MK1, VENDOR_ID};
+use crate::settings::{Backend, Settings};
+use crate::transport::{Transport, TransportError, UsbTransport};
+use crate::utils::get_serial_number;
+use crate::x1_board::X1mk1Board;
+
+const MONITOR_TIMEOUT_MS: i32 = 50;
+
+/// Print every decoded control event of one device until it disconnects.
+/// No MIDI ports are created, so this can run next to DJ software without sending it anything.
+pub fn monitor(settings: &Settings, yaml_config: &YamlConfig, serial: Option<&str>) -> Result<(), String> {
+    let (mut transport, format, serial) = open(settings, serial)?;
+    let board = X1mk1Board::from_yaml(yaml_config);
+    let mut decoder = ReportDecoder::new(format);
+    let mut buf = [0u8; 65];
+    let buf_len = match format {
+        ReportFormat::Hid => 65,
+        ReportFormat::Usb => 24,
+    };
+    let start = Instant::now();
+    println!("Monitoring {} (Ctrl+C to stop)", serial);
+    loop {
+        match transport.read_report(&mut buf[..buf_len], MONITOR_TIMEOUT_MS) {
+            Ok(len) => {
+                let report = match format {
+                    ReportFormat::Hid => hid_payload(&buf[..len]).to_vec(),
+                    ReportFormat::Usb if len == buf_len => buf[..len].to_vec(),
+                    // Partial libusb reads are dropped, as in the driver.
+                    ReportFormat::Usb => continue,
+                };
+                for event in decoder.decode(&report, &board) {
+                    println!("{:>8.3} {}", start.elapsed().as_secs_f32(), event);
+                }
+            }
+            Err(TransportError::Timeout) => {}
+            Err(e) => return Err(format!("{}: {}", serial, e)),
+        }
+    }
+}
+
+fn open(settings: &Settings, serial: Option<&str>) -> Result<(Box<dyn Transport>, ReportFormat, String), String> {
+    match settings.backend {
+        Backend::Hid => {
+            let device = HidDevice::open()?
+                .into_iter()
+                .find(|d| serial.is_none() || serial == Some(d.serial_number.as_str()))
+                .ok_or_else(|| format!("No X1 with serial {} found", serial.unwrap_or_default()))?;
+            Ok((Box::new(device.handle), ReportFormat::Hid, device.serial_number))
+        }
+        Backend::Libusb => {
+            let context = Context::new().map_err(|e| e.to_string())?;
+            for device in context.devices().map_err(|e| e.to_string())?.iter() {
+                let Ok(desc) = device.device_descriptor() else { continue };
+                if desc.vendor_id() != VENDOR_ID || desc.product_id() != PRODUCT_ID_MK1 {
+                    continue;
+                }
+                let device_serial = get_serial_number(&device);
+                if serial.is_some() && serial != Some(device_serial.as_str()) {
+                    continue;
+                }
+                let handle = device.open().map_err(|e| format!("Could not open device: {}", e))?;
+                let mut transport = UsbTransport::new(device, handle, settings.led_debug);
+                transport.configure().map_err(|e| format!("Could not configure device: {}", e))?;
+                return Ok((Box::new(transport), ReportFormat::Usb, device_serial));
+            }
+            Err("No X1 found via libusb".to_string())
+        }
+    }
+}
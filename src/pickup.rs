@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+
+/// Last value known at each knob destination (0-based channel, CC), for soft takeover: what a
+/// `pickup` knob sent there, or what software echoed back on the input port.
+#[derive(Default)]
+pub struct Pickup {
+    values: HashMap<(u8, u8), u8>,
+}
+
+impl Pickup {
+    /// Remember the value of a CC received on the input port.
+    pub fn echo(&mut self, message: &[u8]) {
+        if message.len() >= 3 && message[0] & 0xF0 == 0xB0 {
+            self.values.insert((message[0] & 0x0F, message[1]), message[2] & 0x7F);
+        }
+    }
+
+    /// Whether a knob moving from `prev` to `curr` may send to `channel` and `cc`: it has to reach
+    /// or cross the value known there. Records `curr` when it may. With no value known yet (nothing
+    /// sent there since start and nothing echoed) it may right away, unless it should `wait` for one.
+    pub fn take(&mut self, channel: u8, cc: u8, prev: u8, curr: u8, wait: bool) -> bool {
+        let picked_up = match self.values.get(&(channel, cc)) {
+            Some(&value) => prev.min(curr) <= value && value <= prev.max(curr),
+            None => !wait,
+        };
+        if picked_up {
+            self.values.insert((channel, cc), curr);
+        }
+        picked_up
+    }
+
+    /// The knob at `curr` is away from the value known at `channel` and `cc`, or has no value to
+    /// go to yet and should `wait` for one.
+    pub fn waiting(&self, channel: u8, cc: u8, curr: u8, wait: bool) -> bool {
+        self.values.get(&(channel, cc)).map_or(wait, |&value| value != curr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sends_only_after_crossing_the_known_value() {
+        let mut pickup = Pickup::default();
+        assert!(pickup.take(0, 7, 10, 20, false));
+        assert!(pickup.take(0, 7, 20, 21, false));
+
+        pickup.echo(&[0xB0, 7, 90]);
+        assert!(pickup.waiting(0, 7, 21, false));
+        assert!(!pickup.take(0, 7, 21, 40, false));
+        assert!(pickup.take(0, 7, 85, 95, false));
+        assert!(!pickup.waiting(0, 7, 95, false));
+
+        // Another channel (e.g. a layer) keeps its own value.
+        assert!(pickup.take(2, 7, 95, 96, false));
+        assert!(!pickup.take(0, 7, 20, 30, false));
+    }
+
+    #[test]
+    fn layer_switch_waits_for_the_new_target() {
+        // The knob sends on channel 1, then a layer moves it to channel 3, where nothing is known.
+        let mut pickup = Pickup::default();
+        assert!(!pickup.take(0, 7, 10, 20, true));
+        pickup.echo(&[0xB0, 7, 25]);
+        assert!(pickup.take(0, 7, 20, 30, true));
+
+        assert!(pickup.waiting(2, 7, 30, true));
+        assert!(!pickup.take(2, 7, 30, 40, true));
+        pickup.echo(&[0xB2, 7, 90]);
+        assert!(!pickup.take(2, 7, 40, 50, true));
+        assert!(pickup.take(2, 7, 85, 95, true));
+        assert!(!pickup.waiting(2, 7, 95, true));
+
+        // Back on channel 1 the knob picks up where it left that layer.
+        assert!(!pickup.take(0, 7, 95, 96, true));
+        assert!(pickup.take(0, 7, 40, 25, true));
+
+        // Without `wait` an unknown target takes the knob at once.
+        assert!(!pickup.waiting(5, 7, 30, false));
+        assert!(pickup.take(5, 7, 30, 40, false));
+    }
+}
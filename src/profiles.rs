@@ -0,0 +1,323 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use crate::conf::{YamlConfig, YamlDevice};
+use crate::config_files::user_config_dir;
+use crate::config_watch::{self, SharedConfig, CONFIG_POLL_INTERVAL};
+use crate::validate::{self, ConfigError};
+
+/// The mapping from `--config` / board.yml; always available.
+pub const DEFAULT_PROFILE: &str = "default";
+/// Profile files (`<name>.yml`, each a full board mapping) inside the user config dir.
+const PROFILES_DIR: &str = "profiles";
+/// Remembered profile choices inside the user config dir.
+const STATE_FILE: &str = "state.yml";
+
+pub fn profiles_dir() -> Option<PathBuf> {
+    user_config_dir().map(|dir| dir.join(PROFILES_DIR))
+}
+
+fn state_path() -> Option<PathBuf> {
+    user_config_dir().map(|dir| dir.join(STATE_FILE))
+}
+
+fn profile_path(name: &str) -> Option<PathBuf> {
+    profiles_dir().map(|dir| dir.join(format!("{}.yml", name)))
+}
+
+/// `default` followed by every profile file, sorted by name.
+pub fn profile_names() -> Vec<String> {
+    let mut names: Vec<String> = profiles_dir()
+        .and_then(|dir| std::fs::read_dir(dir).ok())
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().and_then(|e| e.to_str()) == Some("yml"))
+        .filter_map(|path| path.file_stem().and_then(|s| s.to_str()).map(str::to_string))
+        .filter(|name| name != DEFAULT_PROFILE)
+        .collect();
+    names.sort();
+    names.insert(0, DEFAULT_PROFILE.to_string());
+    names
+}
+
+/// Load and validate a named profile.
+pub fn load_profile(name: &str) -> Result<YamlConfig, ConfigError> {
+    let path = profile_path(name).unwrap_or_else(|| PathBuf::from(format!("{}.yml", name)));
+    if !path.exists() {
+        return Err(ConfigError::single(&path, format!("no profile named {:?}", name)));
+    }
+    validate::load_config_file(&path)
+}
+
+/// Which profile each controller uses; written by `profile set` and the on-device switch,
+/// and re-read by a running driver when it changes.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ProfileState {
+    /// Profile for controllers without their own entry.
+    #[serde(default)]
+    pub default: Option<String>,
+    /// Device id (serial) to profile name.
+    #[serde(default)]
+    pub devices: BTreeMap<String, String>,
+}
+
+impl ProfileState {
+    pub fn load() -> Self {
+        let Some(path) = state_path() else { return Self::default() };
+        match std::fs::read_to_string(&path) {
+            Ok(text) => serde_yaml::from_str(&text).unwrap_or_else(|e| {
+                eprintln!("Ignoring {}: {}", path.display(), e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = state_path().ok_or("no user config directory")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        }
+        let text = serde_yaml::to_string(self).map_err(|e| e.to_string())?;
+        std::fs::write(&path, text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn selected(&self, serial: &str) -> &str {
+        self.devices
+            .get(serial)
+            .or(self.default.as_ref())
+            .map(String::as_str)
+            .unwrap_or(DEFAULT_PROFILE)
+    }
+
+    /// Use `name` for one controller, or for all of them (dropping per-device choices) when `serial` is `None`.
+    pub fn select(&mut self, serial: Option<&str>, name: &str) {
+        match serial {
+            Some(serial) => {
+                self.devices.insert(serial.to_string(), name.to_string());
+            }
+            None => {
+                self.devices.clear();
+                self.default = Some(name.to_string());
+            }
+        }
+    }
+}
+
+/// Check that `name` loads, then remember it for `serial` (or every controller).
+pub fn select_profile(serial: Option<&str>, name: &str) -> Result<(), String> {
+    if name != DEFAULT_PROFILE {
+        load_profile(name).map_err(|e| e.to_string())?;
+    }
+    let mut state = ProfileState::load();
+    state.select(serial, name);
+    state.save()
+}
+
+fn state_modified() -> Option<SystemTime> {
+    std::fs::metadata(state_path()?).and_then(|m| m.modified()).ok()
+}
+
+/// Loaded profiles and the current selection, shared by every device handler.
+pub struct Profiles {
+    default: Arc<SharedConfig>,
+    loaded: Mutex<HashMap<String, Arc<SharedConfig>>>,
+    state: Mutex<ProfileState>,
+}
+
+impl Profiles {
+    /// `default` is the mapping from `--config` / board.yml. Starts a thread that picks up
+    /// selections written by `profile set` while the driver runs.
+    pub fn start(default: Arc<SharedConfig>) -> Arc<Self> {
+        let profiles = Arc::new(Self {
+            default,
+            loaded: Mutex::new(HashMap::new()),
+            state: Mutex::new(ProfileState::load()),
+        });
+        let watched = Arc::clone(&profiles);
+        thread::spawn(move || {
+            let mut last = state_modified();
+            loop {
+                thread::sleep(CONFIG_POLL_INTERVAL);
+                let modified = state_modified();
+                if modified != last {
+                    last = modified;
+                    *watched.state.lock().unwrap() = ProfileState::load();
+                }
+            }
+        });
+        profiles
+    }
+
+    /// A choice made for this controller, else its `profile` in `devices:`, else the choice for all.
+    pub fn selected(&self, serial: &str) -> String {
+        let state = self.state.lock().unwrap();
+        if let Some(name) = state.devices.get(serial) {
+            return name.clone();
+        }
+        if let Some(name) = self.device(serial).and_then(|d| d.profile) {
+            return name;
+        }
+        state.selected(serial).to_string()
+    }
+
+    /// The `devices:` section of the default mapping; profiles do not carry their own.
+    pub fn devices(&self) -> Vec<YamlDevice> {
+        self.default.current().1.devices.clone()
+    }
+
+    pub fn device(&self, serial: &str) -> Option<YamlDevice> {
+        self.default.current().1.device(serial).cloned()
+    }
+
+    /// The mapping of profile `name`, loaded and watched for changes on first use.
+    pub fn config(&self, name: &str) -> Result<Arc<SharedConfig>, ConfigError> {
+        if name == DEFAULT_PROFILE {
+            return Ok(Arc::clone(&self.default));
+        }
+        let mut loaded = self.loaded.lock().unwrap();
+        if let Some(shared) = loaded.get(name) {
+            return Ok(Arc::clone(shared));
+        }
+        let shared = Arc::new(SharedConfig::new(load_profile(name)?));
+        if let Some(path) = profile_path(name) {
+            config_watch::watch(Some(path), Arc::clone(&shared));
+        }
+        loaded.insert(name.to_string(), Arc::clone(&shared));
+        Ok(shared)
+    }
+
+    /// Switch `serial` to the profile after its current one, wrapping around, and remember it.
+    pub fn cycle(&self, serial: &str) {
+        let names = profile_names();
+        let selected = self.selected(serial);
+        let current = names.iter().position(|n| *n == selected).unwrap_or(0);
+        let mut state = self.state.lock().unwrap();
+        let next = names[(current + 1) % names.len()].clone();
+        state.select(Some(serial), &next);
+        if let Err(e) = state.save() {
+            eprintln!("Failed to save profile choice: {}", e);
+        }
+    }
+
+    /// Start following the selected profile of `serial`; returns its current mapping.
+    pub fn follow(self: &Arc<Self>, serial: &str) -> (ConfigFollower, Arc<YamlConfig>) {
+        let mut profile = self.selected(serial);
+        let shared = match self.config(&profile) {
+            Ok(shared) => shared,
+            Err(e) => {
+                eprintln!("{}\n{}: using the {} profile", e, serial, DEFAULT_PROFILE);
+                profile = DEFAULT_PROFILE.to_string();
+                self.state.lock().unwrap().select(Some(serial), &profile);
+                Arc::clone(&self.default)
+            }
+        };
+        let (generation, config) = shared.current();
+        let follower = ConfigFollower {
+            profiles: Arc::clone(self),
+            serial: serial.to_string(),
+            profile,
+            shared,
+            generation,
+            devices_generation: self.default.current().0,
+        };
+        (follower, config)
+    }
+}
+
+/// A device handler's view of [`Profiles`]: notices profile switches and reloads of its profile.
+pub struct ConfigFollower {
+    profiles: Arc<Profiles>,
+    serial: String,
+    profile: String,
+    shared: Arc<SharedConfig>,
+    generation: u64,
+    /// Generation of the default mapping, which holds `devices:`.
+    devices_generation: u64,
+}
+
+impl ConfigFollower {
+    /// The mapping to switch to, when the device's profile or its file changed since the last call.
+    pub fn poll(&mut self) -> Option<Arc<YamlConfig>> {
+        let selected = self.profiles.selected(&self.serial);
+        if selected != self.profile {
+            match self.profiles.config(&selected) {
+                Ok(shared) => {
+                    println!("🎛 {} -> profile {}", self.serial, selected);
+                    let (generation, config) = shared.current();
+                    self.profile = selected;
+                    self.shared = shared;
+                    self.generation = generation;
+                    return Some(config);
+                }
+                Err(e) => {
+                    // Remember the failure so it is reported once, not on every poll.
+                    eprintln!("{}\n{}: staying on profile {}", e, self.serial, self.profile);
+                    self.profiles.state.lock().unwrap().select(Some(&self.serial), &self.profile);
+                }
+            }
+        }
+        let (generation, config) = self.shared.newer_than(self.generation)?;
+        self.generation = generation;
+        Some(config)
+    }
+
+    /// The device's `devices:` entry (empty when it has none), when the default mapping changed
+    /// since the last call.
+    pub fn poll_device(&mut self) -> Option<YamlDevice> {
+        let (generation, config) = self.profiles.default.newer_than(self.devices_generation)?;
+        self.devices_generation = generation;
+        Some(config.device(&self.serial).cloned().unwrap_or_default())
+    }
+
+    /// Move to the next profile (on-device switch); takes effect on the next [`Self::poll`].
+    pub fn cycle(&self) {
+        self.profiles.cycle(&self.serial);
+    }
+}
+
+/// Profile files that exist, for `profile list`.
+pub fn describe(state: &ProfileState) -> Vec<String> {
+    let mut lines: Vec<String> = profile_names()
+        .into_iter()
+        .map(|name| {
+            let path = if name == DEFAULT_PROFILE { None } else { profile_path(&name) };
+            let marker = if state.default.as_deref().unwrap_or(DEFAULT_PROFILE) == name { "*" } else { " " };
+            match path {
+                Some(path) => format!("{} {}  {}", marker, name, path.display()),
+                None => format!("{} {}  (board.yml)", marker, name),
+            }
+        })
+        .collect();
+    for (serial, name) in &state.devices {
+        lines.push(format!("  {} -> {}", serial, name));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn device_choice_overrides_default_until_all_are_switched() {
+        let mut state = ProfileState::default();
+        assert_eq!(state.selected("A"), DEFAULT_PROFILE);
+        state.select(None, "mixxx");
+        state.select(Some("A"), "daw");
+        assert_eq!(state.selected("A"), "daw");
+        assert_eq!(state.selected("B"), "mixxx");
+
+        let text = serde_yaml::to_string(&state).unwrap();
+        assert_eq!(serde_yaml::from_str::<ProfileState>(&text).unwrap(), state);
+
+        state.select(None, "djay");
+        assert_eq!(state.selected("A"), "djay");
+    }
+}
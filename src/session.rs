@@ -0,0 +1,33 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+/// Controller state that outlives a device handler, so a reconnect picks up where the
+/// previous handler stopped instead of starting dark and unshifted.
+#[derive(Clone, Debug)]
+pub struct SessionState {
+    pub led: [u8; 32],
+    pub led_hotcue: [u8; 16],
+    pub led_ext: [u8; 58],
+    pub led_bank80: [u8; 51],
+    pub led_bank81: [u8; 90],
+    /// Modifier layers that were on.
+    pub layers: HashSet<String>,
+    /// Last value sent for each knob, by control name.
+    pub knobs: HashMap<String, u8>,
+}
+
+/// Session state of every controller seen since startup, keyed by device id.
+#[derive(Default)]
+pub struct SessionStore {
+    sessions: Mutex<HashMap<String, SessionState>>,
+}
+
+impl SessionStore {
+    pub fn get(&self, serial: &str) -> Option<SessionState> {
+        self.sessions.lock().unwrap().get(serial).cloned()
+    }
+
+    pub fn save(&self, serial: &str, state: SessionState) {
+        self.sessions.lock().unwrap().insert(serial.to_string(), state);
+    }
+}
@@ -0,0 +1,87 @@
+use std::path::PathBuf;
+
+use crate::devices::PortSuffix;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    /// hidapi (default; works on macOS 26.1+ and Linux hidraw)
+    Hid,
+    /// libusb bulk transfers (X1 MK1 fallback)
+    Libusb,
+}
+
+/// LED diagnostics, set by the `led-test` subcommand.
+#[derive(Clone, Debug)]
+pub struct LedTestSettings {
+    /// Walk a single lit LED across the 32-byte frame.
+    pub chase: bool,
+    /// Turn every LED off (all output and feature reports) and stop.
+    pub clear: bool,
+    /// Light every LED.
+    pub all: bool,
+    /// Also write the extended LED range (report 0x81).
+    pub ext: bool,
+    /// Light only this LED index in `bank`.
+    pub idx: Option<usize>,
+    /// LED bank `idx` refers to: 0x80 (51 LEDs), 0x81 (90 LEDs), anything else for the 32-byte frame.
+    pub bank: u8,
+    /// Only write this output report ID; `None` writes every known layout.
+    pub rid: Option<u8>,
+    /// Stop after writing the `idx` frame once.
+    pub once: bool,
+}
+
+impl Default for LedTestSettings {
+    fn default() -> Self {
+        Self {
+            chase: false,
+            clear: false,
+            all: false,
+            ext: false,
+            idx: None,
+            bank: 0x80,
+            rid: Some(0x80),
+            once: false,
+        }
+    }
+}
+
+/// Everything the driver and device handlers need from the command line.
+#[derive(Clone, Debug)]
+pub struct Settings {
+    pub config_path: Option<PathBuf>,
+    pub backend: Backend,
+    pub led_debug: bool,
+    pub log_bytes: bool,
+    /// libusb only: keep driving LEDs even when the input endpoint cannot be configured.
+    pub led_only: bool,
+    pub capture_dir: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    pub menu_icon: Option<PathBuf>,
+    /// Default port naming for controllers without a `port_name` in the config.
+    pub port_suffix: PortSuffix,
+    /// All controllers share one port pair, three MIDI channels each.
+    pub merged_port: bool,
+    /// Profile to switch every controller to at startup.
+    pub profile: Option<String>,
+    pub led_test: LedTestSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            config_path: None,
+            backend: Backend::Hid,
+            led_debug: false,
+            log_bytes: false,
+            led_only: false,
+            capture_dir: None,
+            replay: None,
+            menu_icon: None,
+            port_suffix: PortSuffix::Index,
+            merged_port: false,
+            profile: None,
+            led_test: LedTestSettings::default(),
+        }
+    }
+}
@@ -0,0 +1,278 @@
+use std::fmt;
+#[cfg(test)]
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rusb::{Device, DeviceHandle, UsbContext};
+
+const USB_WRITE_FD: u8 = 0x01;
+const USB_UNLOCK_FD: u8 = 0x81;
+const USB_READ_FD: u8 = 0x84;
+const USB_TIMEOUT_MS: u64 = 50;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransportError {
+    /// No report arrived before the timeout expired.
+    Timeout,
+    /// The device is gone (unplugged, or a mock/replay ran out of reports).
+    Disconnected,
+    Io(String),
+}
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransportError::Timeout => write!(f, "timeout"),
+            TransportError::Disconnected => write!(f, "device disconnected"),
+            TransportError::Io(e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
+
+impl From<rusb::Error> for TransportError {
+    fn from(e: rusb::Error) -> Self {
+        match e {
+            rusb::Error::Timeout => TransportError::Timeout,
+            rusb::Error::NoDevice => TransportError::Disconnected,
+            e => TransportError::Io(e.to_string()),
+        }
+    }
+}
+
+impl From<hidapi::HidError> for TransportError {
+    fn from(e: hidapi::HidError) -> Self {
+        let msg = e.to_string();
+        if msg.contains("timeout") || msg.contains("Timeout") {
+            TransportError::Timeout
+        } else {
+            TransportError::Io(msg)
+        }
+    }
+}
+
+/// Raw report I/O with an X1, independent of how the device is reached.
+pub trait Transport: Send {
+    /// Read one input report into `buf`, waiting at most `timeout_ms`.
+    fn read_report(&mut self, buf: &mut [u8], timeout_ms: i32) -> Result<usize, TransportError>;
+
+    /// Write one output report (LED frame). The first byte is the report ID where the device uses one.
+    fn write_report(&mut self, data: &[u8]) -> Result<usize, TransportError>;
+
+    /// Send a feature report. The first byte is the report ID.
+    fn send_feature_report(&mut self, data: &[u8]) -> Result<(), TransportError>;
+
+    /// Prepare the device for reading. Called once before the first read.
+    fn configure(&mut self) -> Result<(), TransportError> {
+        Ok(())
+    }
+
+    /// Called after a batch of output reports has been written.
+    fn flush(&mut self) {}
+}
+
+impl Transport for hidapi::HidDevice {
+    fn read_report(&mut self, buf: &mut [u8], timeout_ms: i32) -> Result<usize, TransportError> {
+        match self.read_timeout(buf, timeout_ms)? {
+            0 => Err(TransportError::Timeout),
+            n => Ok(n),
+        }
+    }
+
+    fn write_report(&mut self, data: &[u8]) -> Result<usize, TransportError> {
+        Ok(self.write(data)?)
+    }
+
+    fn send_feature_report(&mut self, data: &[u8]) -> Result<(), TransportError> {
+        Ok(hidapi::HidDevice::send_feature_report(self, data)?)
+    }
+}
+
+struct Endpoint {
+    config: u8,
+    interface: u8,
+    setting: u8,
+    address: u8,
+}
+
+/// libusb transport: bulk reads from the input endpoint, bulk writes to every OUT endpoint.
+pub struct UsbTransport<T: UsbContext> {
+    device: Device<T>,
+    handle: DeviceHandle<T>,
+    endpoint: Endpoint,
+    out_endpoints: Vec<u8>,
+    timeout: Duration,
+    debug: bool,
+}
+
+impl<T: UsbContext> UsbTransport<T> {
+    pub fn new(device: Device<T>, handle: DeviceHandle<T>, debug: bool) -> Self {
+        Self {
+            device,
+            handle,
+            endpoint: Endpoint {
+                address: USB_READ_FD,
+                config: 1,
+                interface: 0,
+                setting: 0,
+            },
+            out_endpoints: vec![USB_WRITE_FD],
+            timeout: Duration::from_millis(USB_TIMEOUT_MS),
+            debug,
+        }
+    }
+}
+
+impl<T: UsbContext> Transport for UsbTransport<T> {
+    fn read_report(&mut self, buf: &mut [u8], timeout_ms: i32) -> Result<usize, TransportError> {
+        let timeout = Duration::from_millis(timeout_ms.max(0) as u64);
+        Ok(self.handle.read_bulk(self.endpoint.address, buf, timeout)?)
+    }
+
+    fn write_report(&mut self, data: &[u8]) -> Result<usize, TransportError> {
+        let mut result = Err(TransportError::Disconnected);
+        for ep in &self.out_endpoints {
+            match self.handle.write_bulk(*ep, data, self.timeout) {
+                Ok(n) => {
+                    if self.debug {
+                        eprintln!("[LED BULK] ok ep=0x{:02x} len={} wrote={}", ep, data.len(), n);
+                    }
+                    result = Ok(n);
+                }
+                Err(e) => {
+                    if self.debug {
+                        eprintln!("[LED BULK] err ep=0x{:02x} len={} err={:?}", ep, data.len(), e);
+                    }
+                    if result.is_err() {
+                        result = Err(e.into());
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    fn send_feature_report(&mut self, data: &[u8]) -> Result<(), TransportError> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        // HID class SET_REPORT (feature) on the control endpoint.
+        let value = 0x0300 | data[0] as u16;
+        self.handle.write_control(0x21, 0x09, value, self.endpoint.interface as u16, &data[1..], self.timeout)?;
+        Ok(())
+    }
+
+    fn configure(&mut self) -> Result<(), TransportError> {
+        if let Err(e) = self.handle.set_auto_detach_kernel_driver(true) {
+            if self.debug {
+                eprintln!("[USB] auto_detach_kernel_driver failed: {:?}", e);
+            }
+        }
+        if let Err(e) = self.handle.set_active_configuration(self.endpoint.config) {
+            if self.debug {
+                eprintln!("[USB] set_active_configuration failed: {:?}", e);
+            }
+            return Err(e.into());
+        }
+        // Try without claiming interface first - the kernel HID driver might handle this
+        match self.handle.claim_interface(self.endpoint.interface) {
+            Ok(_) => println!("Successfully claimed interface"),
+            Err(e) => {
+                println!("Could not claim interface (continuing anyway): {:?}", e);
+                // Try detach across all interfaces
+                if let Ok(config) = self.device.active_config_descriptor() {
+                    for interface in config.interfaces() {
+                        let iface = interface.number();
+                        if let Err(e2) = self.handle.detach_kernel_driver(iface) {
+                            if self.debug {
+                                eprintln!("[USB] detach_kernel_driver iface={} failed: {:?}", iface, e2);
+                            }
+                        } else if self.debug {
+                            eprintln!("[USB] detached kernel driver on iface={}", iface);
+                        }
+                    }
+                }
+                let _ = self.handle.claim_interface(self.endpoint.interface);
+            }
+        }
+        if let Err(e) = self.handle.set_alternate_setting(self.endpoint.interface, self.endpoint.setting) {
+            if self.debug {
+                eprintln!("[USB] set_alternate_setting failed: {:?}", e);
+            }
+            return Err(e.into());
+        }
+        if let Ok(config) = self.device.active_config_descriptor() {
+            let mut outs = Vec::new();
+            for interface in config.interfaces() {
+                for descriptor in interface.descriptors() {
+                    for ep in descriptor.endpoint_descriptors() {
+                        let addr = ep.address();
+                        if ep.direction() == rusb::Direction::Out {
+                            outs.push(addr);
+                        }
+                        if self.debug {
+                            eprintln!(
+                                "[USB EP] iface={} alt={} addr=0x{:02x} dir={:?} xfer={:?} max_packet={}",
+                                descriptor.interface_number(),
+                                descriptor.setting_number(),
+                                addr,
+                                ep.direction(),
+                                ep.transfer_type(),
+                                ep.max_packet_size()
+                            );
+                        }
+                    }
+                }
+            }
+            if !outs.is_empty() {
+                outs.sort();
+                outs.dedup();
+                self.out_endpoints = outs;
+            }
+            if self.debug {
+                eprintln!("[USB EP] out_endpoints={:02x?}", self.out_endpoints);
+            }
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) {
+        // The X1 MK1 needs a read on the unlock endpoint before it accepts the next LED frame.
+        let _ = self.handle.read_bulk(USB_UNLOCK_FD, &mut [0; 1], self.timeout);
+    }
+}
+
+/// In-memory transport that records the output reports written to it. It has no input: reads fail
+/// with `Disconnected`, so a handler's read loop ends.
+#[cfg(test)]
+#[derive(Default)]
+pub struct MockTransport {
+    writes: Arc<Mutex<Vec<Vec<u8>>>>,
+}
+
+#[cfg(test)]
+impl MockTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Shared view of the output reports written so far; stays valid after the transport is moved.
+    pub fn writes(&self) -> Arc<Mutex<Vec<Vec<u8>>>> {
+        Arc::clone(&self.writes)
+    }
+}
+
+#[cfg(test)]
+impl Transport for MockTransport {
+    fn read_report(&mut self, _buf: &mut [u8], _timeout_ms: i32) -> Result<usize, TransportError> {
+        Err(TransportError::Disconnected)
+    }
+
+    fn write_report(&mut self, data: &[u8]) -> Result<usize, TransportError> {
+        self.writes.lock().unwrap().push(data.to_vec());
+        Ok(data.len())
+    }
+
+    fn send_feature_report(&mut self, _data: &[u8]) -> Result<(), TransportError> {
+        Ok(())
+    }
+}
@@ -0,0 +1,641 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::conf::{YamlButton, YamlButtonType, YamlConfig, YamlCurve, YamlEncoding, YamlMessageType, YamlModifierMode, YamlOutput};
+use crate::merged_port::CHANNELS_PER_DEVICE;
+use crate::midi_message::CC_LSB_OFFSET;
+
+/// Bytes in an HID input report (without report ID).
+const REPORT_LEN: u8 = 64;
+/// LEDs in the default output report (0x80).
+const LED_COUNT: u8 = 51;
+/// Most readings a knob's `smoothing` may average over.
+const MAX_SMOOTHING_WINDOW: u8 = 32;
+/// Largest step multiplier of an encoder's `acceleration`.
+const MAX_ACCELERATION: u8 = 16;
+/// Highest `channel_offset` that keeps every channel a controller uses (four on libusb) within 1-16.
+const MAX_CHANNEL_OFFSET: u8 = 12;
+
+/// One problem in a board config.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfigIssue {
+    /// 1-based line of the control's `name:` entry, when it could be located.
+    pub line: Option<usize>,
+    pub control: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.line, &self.control) {
+            (Some(line), Some(name)) => write!(f, "line {} ({}): {}", line, name, self.message),
+            (None, Some(name)) => write!(f, "{}: {}", name, self.message),
+            (Some(line), None) => write!(f, "line {}: {}", line, self.message),
+            (None, None) => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// A config that could not be used, with everything wrong with it.
+#[derive(Debug)]
+pub struct ConfigError {
+    pub path: PathBuf,
+    pub issues: Vec<ConfigIssue>,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid config {}:", self.path.display())?;
+        for issue in &self.issues {
+            write!(f, "\n  {}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+impl ConfigError {
+    pub fn single(path: &Path, message: String) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            issues: vec![ConfigIssue { line: None, control: None, message }],
+        }
+    }
+}
+
+/// Parse and validate a board config; `path` is only used in the report.
+pub fn parse_config(path: &Path, text: &str) -> Result<YamlConfig, ConfigError> {
+    let config: YamlConfig = serde_yaml::from_str(text).map_err(|e| ConfigError::single(path, e.to_string()))?;
+    check(path, config, text)
+}
+
+/// Validate an already parsed config; line numbers are looked up in `source`.
+pub fn check(path: &Path, config: YamlConfig, source: &str) -> Result<YamlConfig, ConfigError> {
+    let issues = validate(&config, source);
+    if issues.is_empty() {
+        Ok(config)
+    } else {
+        Err(ConfigError {
+            path: path.to_path_buf(),
+            issues,
+        })
+    }
+}
+
+pub fn load_config_file(path: &Path) -> Result<YamlConfig, ConfigError> {
+    let text = std::fs::read_to_string(path).map_err(|e| ConfigError::single(path, e.to_string()))?;
+    parse_config(path, &text)
+}
+
+/// Every problem in `config`. `source` is the YAML text it was parsed from, used for line numbers.
+pub fn validate(config: &YamlConfig, source: &str) -> Vec<ConfigIssue> {
+    let lines = control_lines(config, source);
+    let layers: Vec<&str> = config.modifiers.iter().map(|m| m.layer.as_str()).collect();
+    let mut seen_names: HashMap<&str, usize> = HashMap::new();
+    let mut issues = Vec::new();
+    for (button, &line) in config.buttons.iter().zip(&lines) {
+        let occurrence = seen_names.entry(button.name.as_str()).or_insert(0);
+        *occurrence += 1;
+        let mut issue = |message: String| {
+            issues.push(ConfigIssue {
+                line,
+                control: Some(button.name.clone()),
+                message,
+            })
+        };
+        if *occurrence == 2 {
+            issue("duplicate control name".to_string());
+        }
+        for message in control_issues(button, &layers) {
+            issue(message);
+        }
+    }
+    issues.extend(duplicate_ccs(config, &lines));
+    issues.extend(device_issues(config));
+    issues.extend(modifier_issues(config));
+    for name in &config.profile_switch {
+        let button = config.buttons.iter().find(|b| &b.name == name);
+        let message = match button.map(|b| &b.button_type) {
+            None => "profile_switch names an unknown control",
+            Some(YamlButtonType::Knob | YamlButtonType::Encoder) => "profile_switch needs buttons, not knobs or encoders",
+            Some(_) => continue,
+        };
+        issues.push(ConfigIssue {
+            line: None,
+            control: Some(name.clone()),
+            message: message.to_string(),
+        });
+    }
+    issues.sort_by_key(|issue| issue.line.unwrap_or(usize::MAX));
+    issues
+}
+
+fn control_issues(button: &YamlButton, layers: &[&str]) -> Vec<String> {
+    let mut issues = Vec::new();
+    if button.read_i >= REPORT_LEN {
+        issues.push(format!("read_i {} is beyond the {}-byte report", button.read_i, REPORT_LEN));
+    }
+    match button.button_type {
+        YamlButtonType::Toggle | YamlButtonType::Hold | YamlButtonType::Hotcue => match button.read_j {
+            None => issues.push(format!("{:?} needs read_j (bit 0-7)", button.button_type)),
+            Some(bit) if bit > 7 => issues.push(format!("read_j {} is not a bit number (0-7)", bit)),
+            Some(_) => {}
+        },
+        YamlButtonType::Knob => match button.read_j {
+            None => issues.push("Knob needs read_j (high-nibble byte)".to_string()),
+            Some(byte) if byte >= REPORT_LEN => {
+                issues.push(format!("read_j {} is beyond the {}-byte report", byte, REPORT_LEN))
+            }
+            Some(_) => {}
+        },
+        YamlButtonType::Encoder => match button.read_pos {
+            None => issues.push("Encoder needs read_pos ('s' or 'e')".to_string()),
+            Some('s') | Some('e') => {}
+            Some(other) => issues.push(format!("read_pos {:?} must be 's' (low nibble) or 'e' (high nibble)", other)),
+        },
+    }
+    if let Some(idx) = button.write_idx {
+        if idx >= LED_COUNT {
+            issues.push(format!("write_idx {} is beyond the {} LEDs of the output report", idx, LED_COUNT));
+        }
+    }
+    if button.midi_ctrl_ch > 127 {
+        issues.push(format!("midi_ctrl_ch {} is above 127", button.midi_ctrl_ch));
+    }
+    if let Some(msb) = button.nrpn_msb {
+        if msb > 127 {
+            issues.push(format!("nrpn_msb {} is above 127", msb));
+        }
+    }
+    issues.extend(output_issues(&YamlOutput {
+        channel: button.channel,
+        cc: None,
+    }));
+    if button.high_res == Some(true) {
+        let kind = button.message.unwrap_or_default();
+        match (&button.button_type, kind) {
+            (YamlButtonType::Knob, YamlMessageType::PitchBend | YamlMessageType::Nrpn) => {}
+            (YamlButtonType::Knob, YamlMessageType::Cc) if button.midi_ctrl_ch < CC_LSB_OFFSET => {}
+            (YamlButtonType::Knob, YamlMessageType::Cc) => issues.push(format!(
+                "high_res CC {} has no LSB partner; use CC 0-31 (LSB on CC + 32)",
+                button.midi_ctrl_ch
+            )),
+            (YamlButtonType::Knob, _) => issues.push(format!("high_res needs CC, PitchBend or NRPN, not {:?}", kind)),
+            _ => issues.push("high_res is only for knobs".to_string()),
+        }
+    }
+    issues.extend(response_issues(button));
+    issues.extend(pickup_issues(button));
+    issues.extend(filter_issues(button));
+    issues.extend(encoder_issues(button));
+    issues.extend(gesture_issues(button));
+    for (layer, output) in &button.layers {
+        if !layers.contains(&layer.as_str()) {
+            issues.push(format!("layers names {:?}, which no modifier switches", layer));
+        }
+        issues.extend(output_issues(&output.output()));
+    }
+    issues
+}
+
+fn response_issues(button: &YamlButton) -> Vec<String> {
+    let mut issues = Vec::new();
+    let shaped = button.curve.is_some() || button.detent.is_some() || button.curve_table.is_some() || button.invert.is_some();
+    let ranged = button.min.is_some() || button.max.is_some();
+    let knob = matches!(button.button_type, YamlButtonType::Knob);
+    let absolute = button.encoding == Some(YamlEncoding::Absolute);
+    if shaped && !knob {
+        issues.push("curve, detent, curve_table and invert are only for knobs".to_string());
+        return issues;
+    }
+    if ranged && !knob && !absolute {
+        issues.push("min and max are only for knobs and Absolute encoders".to_string());
+        return issues;
+    }
+    let (min, max) = (button.min.unwrap_or(0), button.max.unwrap_or(127));
+    if max > 127 {
+        issues.push(format!("max {} is above 127", max));
+    }
+    if min > max {
+        issues.push(format!("min {} is above max {}", min, max));
+    }
+    if let Some(detent) = button.detent {
+        if !(0.0..1.0).contains(&detent) {
+            issues.push(format!("detent {} must be at least 0 and below 1", detent));
+        }
+        if button.curve != Some(YamlCurve::CenterDetent) {
+            issues.push("detent needs curve CenterDetent".to_string());
+        }
+    }
+    match (&button.curve, &button.curve_table) {
+        (Some(YamlCurve::Table), None) => issues.push("curve Table needs curve_table".to_string()),
+        (Some(YamlCurve::Table), Some(table)) => {
+            if table.len() < 2 {
+                issues.push("curve_table needs at least 2 values".to_string());
+            }
+            if let Some(value) = table.iter().find(|&&v| v > 127) {
+                issues.push(format!("curve_table value {} is above 127", value));
+            }
+        }
+        (_, Some(_)) => issues.push("curve_table needs curve Table".to_string()),
+        _ => {}
+    }
+    issues
+}
+
+fn pickup_issues(button: &YamlButton) -> Vec<String> {
+    let mut issues = Vec::new();
+    let pickup = button.pickup == Some(true);
+    let options = button.pickup.is_some() || button.pickup_led.is_some() || button.pickup_wait.is_some();
+    if options && !matches!(button.button_type, YamlButtonType::Knob) {
+        issues.push("pickup, pickup_led and pickup_wait are only for knobs".to_string());
+        return issues;
+    }
+    if button.pickup_wait == Some(true) && !pickup {
+        issues.push("pickup_wait needs pickup: true".to_string());
+    }
+    if pickup && button.message.unwrap_or_default() != YamlMessageType::Cc {
+        issues.push("pickup needs CC messages".to_string());
+    }
+    if let Some(idx) = button.pickup_led {
+        if !pickup {
+            issues.push("pickup_led needs pickup: true".to_string());
+        }
+        if idx >= LED_COUNT {
+            issues.push(format!("pickup_led {} is beyond the {} LEDs of the output report", idx, LED_COUNT));
+        }
+    }
+    issues
+}
+
+fn filter_issues(button: &YamlButton) -> Vec<String> {
+    let mut issues = Vec::new();
+    let filtered = button.deadband.is_some() || button.smoothing.is_some() || button.smoothing_window.is_some();
+    if filtered && !matches!(button.button_type, YamlButtonType::Knob) {
+        issues.push("deadband, smoothing and smoothing_window are only for knobs".to_string());
+        return issues;
+    }
+    if let Some(deadband) = button.deadband {
+        if deadband >= 0x0FFF {
+            issues.push(format!("deadband {} covers the whole 12-bit range", deadband));
+        }
+    }
+    if let Some(window) = button.smoothing_window {
+        if button.smoothing.is_none() {
+            issues.push("smoothing_window needs smoothing".to_string());
+        }
+        if !(1..=MAX_SMOOTHING_WINDOW).contains(&window) {
+            issues.push(format!("smoothing_window {} is not 1-{}", window, MAX_SMOOTHING_WINDOW));
+        }
+    }
+    issues
+}
+
+fn encoder_issues(button: &YamlButton) -> Vec<String> {
+    let mut issues = Vec::new();
+    let turned = button.encoding.is_some() || button.note_down.is_some() || button.acceleration.is_some();
+    if turned && !matches!(button.button_type, YamlButtonType::Encoder) {
+        issues.push("encoding, note_down and acceleration are only for encoders".to_string());
+        return issues;
+    }
+    let notes = button.encoding == Some(YamlEncoding::Notes);
+    if notes && !matches!(button.message, None | Some(YamlMessageType::Note)) {
+        issues.push("encoding Notes sends notes; leave out message".to_string());
+    }
+    if let Some(note) = button.note_down {
+        if !notes {
+            issues.push("note_down needs encoding Notes".to_string());
+        }
+        if note > 127 {
+            issues.push(format!("note_down {} is above 127", note));
+        }
+    }
+    if let Some(acceleration) = button.acceleration {
+        if !(1..=MAX_ACCELERATION).contains(&acceleration) {
+            issues.push(format!("acceleration {} is not 1-{}", acceleration, MAX_ACCELERATION));
+        }
+    }
+    issues
+}
+
+fn gesture_issues(button: &YamlButton) -> Vec<String> {
+    let mut issues = Vec::new();
+    let Some(gestures) = &button.gestures else { return issues };
+    if matches!(button.button_type, YamlButtonType::Knob | YamlButtonType::Encoder) {
+        issues.push("gestures are only for buttons".to_string());
+        return issues;
+    }
+    if button.momentary == Some(true) {
+        issues.push("gestures replace momentary; use one or the other".to_string());
+    }
+    let held = gestures.long_press.is_some() || gestures.long_press_ms.is_some();
+    if gestures.repeat_ms.is_some() && (held || gestures.double_tap.is_some()) {
+        issues.push("repeat_ms cannot be combined with long_press or double_tap".to_string());
+    }
+    if gestures.double_tap_ms.is_some() && gestures.double_tap.is_none() {
+        issues.push("double_tap_ms needs double_tap".to_string());
+    }
+    if gestures.repeat_delay_ms.is_some() && gestures.repeat_ms.is_none() {
+        issues.push("repeat_delay_ms needs repeat_ms".to_string());
+    }
+    if gestures.double_tap == Some(YamlOutput::default()) {
+        issues.push("double_tap needs a channel or cc".to_string());
+    }
+    if gestures.repeat_ms == Some(0) {
+        issues.push("repeat_ms must be above 0".to_string());
+    }
+    for output in gestures.long_press.iter().chain(&gestures.double_tap) {
+        issues.extend(output_issues(output));
+    }
+    issues
+}
+
+fn output_issues(output: &YamlOutput) -> Vec<String> {
+    let mut issues = Vec::new();
+    if let Some(channel) = output.channel {
+        if !(1..=16).contains(&channel) {
+            issues.push(format!("channel {} is not a MIDI channel (1-16)", channel));
+        }
+    }
+    if let Some(cc) = output.cc {
+        if cc > 127 {
+            issues.push(format!("CC {} is above 127", cc));
+        }
+    }
+    issues
+}
+
+fn modifier_issues(config: &YamlConfig) -> Vec<ConfigIssue> {
+    let mut issues = Vec::new();
+    for (idx, modifier) in config.modifiers.iter().enumerate() {
+        let mut issue = |message: String| {
+            issues.push(ConfigIssue {
+                line: None,
+                control: Some(format!("modifier {}", modifier.layer)),
+                message,
+            })
+        };
+        let earlier = &config.modifiers[..idx];
+        if earlier.iter().any(|m| m.layer == modifier.layer) {
+            issue("duplicate layer name".to_string());
+        }
+        if earlier.iter().any(|m| m.button == modifier.button) {
+            issue(format!("{} already switches another layer", modifier.button));
+        }
+        match config.buttons.iter().find(|b| b.name == modifier.button) {
+            None => issue(format!("button {:?} is not a control", modifier.button)),
+            Some(b) if matches!(b.button_type, YamlButtonType::Knob | YamlButtonType::Encoder) => {
+                issue(format!("{} is not a button", modifier.button))
+            }
+            Some(b) if b.gestures.is_some() => issue(format!("{} has gestures; a modifier button cannot", modifier.button)),
+            Some(_) => {}
+        }
+        let output = YamlOutput {
+            channel: modifier.channel,
+            cc: None,
+        };
+        for message in output_issues(&output) {
+            issue(message);
+        }
+    }
+    issues
+}
+
+/// Outputs on channels a controller does not own on the `--merged-port` port (1-3).
+pub fn merged_port_issues(config: &YamlConfig) -> Vec<ConfigIssue> {
+    let mut issues = Vec::new();
+    let mut check = |control: String, channel: Option<u8>| {
+        if let Some(channel) = channel.filter(|&c| c > CHANNELS_PER_DEVICE) {
+            issues.push(ConfigIssue {
+                line: None,
+                control: Some(control),
+                message: format!("channel {} is above {}, the last channel of a controller on the merged port", channel, CHANNELS_PER_DEVICE),
+            });
+        }
+    };
+    for button in &config.buttons {
+        check(button.name.clone(), button.channel);
+        for (layer, output) in &button.layers {
+            check(format!("{} (layer {})", button.name, layer), output.output().channel);
+        }
+        if let Some(gestures) = &button.gestures {
+            for output in gestures.long_press.iter().chain(&gestures.double_tap) {
+                check(format!("{} (gestures)", button.name), output.channel);
+            }
+        }
+    }
+    for modifier in &config.modifiers {
+        check(format!("modifier {}", modifier.layer), modifier.channel);
+    }
+    issues
+}
+
+fn device_issues(config: &YamlConfig) -> Vec<ConfigIssue> {
+    let mut issues = Vec::new();
+    let mut ids: Vec<&str> = Vec::new();
+    for device in &config.devices {
+        let mut issue = |message: String| {
+            issues.push(ConfigIssue {
+                line: None,
+                control: Some(format!("device {}", device.serial)),
+                message,
+            })
+        };
+        for id in std::iter::once(device.serial.as_str()).chain(device.alias.as_deref()) {
+            if ids.contains(&id) {
+                issue(format!("{:?} is used by more than one device", id));
+            }
+            ids.push(id);
+        }
+        if let Some(brightness) = device.led_brightness {
+            if brightness > 127 {
+                issue(format!("led_brightness {} is above 127", brightness));
+            }
+        }
+        if let Some(offset) = device.channel_offset {
+            if offset > MAX_CHANNEL_OFFSET {
+                issue(format!("channel_offset {} is above {}", offset, MAX_CHANNEL_OFFSET));
+            }
+        }
+    }
+    issues
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Layer {
+    Normal,
+    Hotcue,
+}
+
+impl Layer {
+    /// Hotcue buttons only send in hotcue mode and `hotcue_ignore` controls only outside it.
+    fn contains(self, button: &YamlButton) -> bool {
+        match self {
+            Layer::Normal => !matches!(button.button_type, YamlButtonType::Hotcue),
+            Layer::Hotcue => !button.hotcue_ignore.unwrap_or(false),
+        }
+    }
+}
+
+type Address = (YamlMessageType, u8, u16);
+
+/// What a control's messages are told apart by: type, channel and number (pitch bend has none).
+/// A 14-bit CC also occupies its LSB CC.
+fn addresses(button: &YamlButton) -> Vec<Address> {
+    let kind = button.message.unwrap_or_default();
+    let channel = button.channel.unwrap_or(1);
+    let number = button.midi_ctrl_ch as u16;
+    match kind {
+        YamlMessageType::PitchBend => vec![(kind, channel, 0)],
+        YamlMessageType::Nrpn => vec![(kind, channel, (button.nrpn_msb.unwrap_or(0) as u16) << 7 | number)],
+        YamlMessageType::Cc if button.high_res == Some(true) => {
+            vec![(kind, channel, number), (kind, channel, number + CC_LSB_OFFSET as u16)]
+        }
+        _ => vec![(kind, channel, number)],
+    }
+}
+
+fn describe_address((kind, channel, number): Address) -> String {
+    let message = match kind {
+        YamlMessageType::Cc => format!("CC {}", number),
+        YamlMessageType::Note => format!("note {}", number),
+        YamlMessageType::ProgramChange => format!("program {}", number),
+        YamlMessageType::PitchBend => "pitch bend".to_string(),
+        YamlMessageType::Nrpn => format!("NRPN {}", number),
+    };
+    if channel == 1 {
+        message
+    } else {
+        format!("{} on channel {}", message, channel)
+    }
+}
+
+/// Controls sending the same message while the same hotcue mode is active; each is reported once.
+/// Latching modifiers send nothing and are left out.
+fn duplicate_ccs(config: &YamlConfig, lines: &[Option<usize>]) -> Vec<ConfigIssue> {
+    let mut issues: Vec<ConfigIssue> = Vec::new();
+    let silent = |button: &YamlButton| {
+        config
+            .modifiers
+            .iter()
+            .any(|m| m.button == button.name && m.mode == YamlModifierMode::Latching)
+    };
+    for layer in [Layer::Normal, Layer::Hotcue] {
+        let mut by_address: HashMap<Address, &str> = HashMap::new();
+        let sending = config.buttons.iter().zip(lines).filter(|(b, _)| layer.contains(b) && !silent(b));
+        for (button, &line) in sending {
+            for address in addresses(button) {
+                let Some(first) = by_address.get(&address) else {
+                    by_address.insert(address, &button.name);
+                    continue;
+                };
+                let control = Some(button.name.clone());
+                if *first == button.name || issues.iter().any(|issue| issue.control == control) {
+                    continue;
+                }
+                issues.push(ConfigIssue {
+                    line,
+                    control,
+                    message: format!("{} is already used by {} ({:?} layer)", describe_address(address), first, layer),
+                });
+            }
+        }
+    }
+    issues
+}
+
+/// Line of each control's `name:` entry, in config order; a repeated name maps to its next occurrence.
+fn control_lines(config: &YamlConfig, source: &str) -> Vec<Option<usize>> {
+    let lines = name_lines(source);
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    config
+        .buttons
+        .iter()
+        .map(|button| {
+            let occurrence = seen.entry(button.name.as_str()).or_insert(0);
+            *occurrence += 1;
+            lines.get(button.name.as_str()).and_then(|l| l.get(*occurrence - 1)).copied()
+        })
+        .collect()
+}
+
+/// Line numbers of every `name:` entry, by control name, in file order.
+fn name_lines(source: &str) -> HashMap<&str, Vec<usize>> {
+    let mut lines: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (idx, line) in source.lines().enumerate() {
+        let trimmed = line.trim_start().trim_start_matches("- ").trim_start();
+        if let Some(value) = trimmed.strip_prefix("name:") {
+            let name = value.split('#').next().unwrap_or("").trim().trim_matches(|c| c == '"' || c == '\'');
+            lines.entry(name).or_default().push(idx + 1);
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_board_is_valid() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("board.yml");
+        if let Err(e) = load_config_file(&path) {
+            panic!("{}", e);
+        }
+    }
+
+    #[test]
+    fn reports_every_problem_with_its_line() {
+        let source = r#"buttons:
+  - name: "A"
+    type: "Knob"
+    read_i: 70
+    midi_ctrl_ch: 1
+  - name: "B"
+    type: "Hold"
+    read_i: 10
+    read_j: 2
+    write_idx: 60
+    midi_ctrl_ch: 1
+  - name: "B"
+    type: "Encoder"
+    read_i: 12
+    midi_ctrl_ch: 2
+"#;
+        let err = parse_config(Path::new("test.yml"), source).unwrap_err();
+        let found: Vec<(Option<usize>, &str)> = err
+            .issues
+            .iter()
+            .map(|issue| (issue.line, issue.control.as_deref().unwrap_or("")))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (Some(2), "A"),
+                (Some(2), "A"),
+                (Some(6), "B"),
+                (Some(6), "B"),
+                (Some(12), "B"),
+                (Some(12), "B"),
+            ]
+        );
+        let report = err.to_string();
+        assert!(report.contains("line 2 (A): read_i 70 is beyond the 64-byte report"), "{}", report);
+        assert!(report.contains("line 2 (A): Knob needs read_j"), "{}", report);
+        assert!(report.contains("line 6 (B): CC 1 is already used by A"), "{}", report);
+        assert!(report.contains("line 12 (B): duplicate control name"), "{}", report);
+    }
+
+    #[test]
+    fn merged_port_keeps_outputs_on_three_channels() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("board.yml");
+        let mut config = load_config_file(&path).unwrap();
+        assert!(merged_port_issues(&config).is_empty());
+
+        config.buttons[0].channel = Some(4);
+        config.modifiers[0].channel = Some(16);
+        let issues = merged_port_issues(&config);
+        assert_eq!(issues.len(), 2);
+        assert!(issues[0].to_string().starts_with("SHIFT: channel 4 is above 3"), "{}", issues[0]);
+    }
+}
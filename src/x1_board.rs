@@ -1,7 +1,13 @@
 use std::collections::HashMap;
-use std::time::Instant;
 
-use crate::conf::{YamlButtonType, YamlConfig};
+use crate::conf::{YamlButtonType, YamlConfig, YamlModifierMode};
+use crate::curve::Response;
+use crate::decoder::{knob_full_scale, knob_raw};
+use crate::encoder_output::EncoderOutput;
+use crate::gesture::{Gesture, Gestures};
+use crate::knob_filter::KnobFilter;
+use crate::layers::{layer_outputs, LayerOutputs, Layers};
+use crate::midi_message::Message;
 
 pub struct Button {
     pub curr: bool,
@@ -10,17 +16,71 @@ pub struct Button {
     pub read_j: u8,
     pub write_idx: u8,
     pub midi_ctrl_ch: u8,
+    pub message: Message,
     pub hotcue_ignore: bool,
+    pub momentary: bool,
+    pub rgb_led: bool,
+    pub gestures: Option<Gestures>,
+    /// `gestures` is the long press of `long_press` modifiers, only used while one is on.
+    pub layer_long_press: bool,
+    pub layers: LayerOutputs,
     pub debounce_count: u8,  // Track consecutive identical reads for this button
-    pub press_time: Option<Instant>,
 }
 
 pub struct Knob {
     pub curr: u8,
     pub prev: u8,
+    /// Last 14-bit reading, for `high_res` knobs.
+    pub fine: u16,
+    pub response: Response,
+    pub filter: KnobFilter,
+    pub pickup: bool,
+    pub pickup_led: Option<u8>,
+    pub pickup_wait: bool,
     pub read_i: u8,
     pub read_j: u8,
     pub midi_ctrl_ch: u8,
+    pub message: Message,
+    pub layers: LayerOutputs,
+}
+
+impl Button {
+    /// The gesture recognizer for a press (`pressed`) or release: the button's own, or the long
+    /// press of a `long_press` modifier that was on when the button went down.
+    pub fn recognizer(&mut self, layers: &Layers, pressed: bool) -> Option<&mut Gestures> {
+        let gestures = self.gestures.as_mut()?;
+        let engaged = if pressed { layers.long_press() } else { gestures.is_pressed() };
+        (!self.layer_long_press || engaged).then_some(gestures)
+    }
+
+    /// Messages for `gestures` recognized on this button; `channel` is its default channel.
+    pub fn gesture_messages(&self, layers: &Layers, channel: u8, gestures: &[Gesture]) -> Vec<Vec<u8>> {
+        let Some(recognizer) = &self.gestures else { return Vec::new() };
+        let channel = self.message.channel.unwrap_or(channel);
+        let (channel, number) = layers.output(&self.layers, channel, self.midi_ctrl_ch);
+        let mut messages = Vec::new();
+        for &gesture in gestures {
+            let (channel, number) = recognizer.address(gesture, channel, number);
+            messages.extend(self.message.button(channel, number, true));
+            messages.extend(self.message.button(channel, number, false));
+        }
+        messages
+    }
+}
+
+impl Knob {
+    /// Filtered reading of `report` as a fraction of the travel, 0.0-1.0.
+    pub fn position(&mut self, report: &[u8]) -> f64 {
+        let full_scale = knob_full_scale(self.read_i, self.read_j);
+        self.filter.update(knob_raw(report, self.read_i, self.read_j), full_scale) as f64 / full_scale as f64
+    }
+
+    /// Like [`Self::position`], but taking the reading as is, as the baseline for later ones.
+    pub fn reset(&mut self, report: &[u8]) -> f64 {
+        let raw = knob_raw(report, self.read_i, self.read_j);
+        self.filter.reset(raw);
+        raw as f64 / knob_full_scale(self.read_i, self.read_j) as f64
+    }
 }
 
 pub struct Encoder {
@@ -29,6 +89,9 @@ pub struct Encoder {
     pub read_pos: char,
     pub read_i: u8,
     pub midi_ctrl_ch: u8,
+    pub message: Message,
+    pub output: EncoderOutput,
+    pub layers: LayerOutputs,
 }
 
 pub enum ButtonType {
@@ -41,62 +104,63 @@ pub enum ButtonType {
 
 pub struct X1mk1Board {
     pub(crate) buttons: HashMap<String, ButtonType>,
+    /// `profile_switch` buttons from the config.
+    pub(crate) profile_combo: Vec<String>,
 }
 
 impl X1mk1Board {
+    /// Build the board from a config that passed [`crate::validate::validate`], which guarantees
+    /// the fields defaulted here are present.
     pub(crate) fn from_yaml(yaml_config: &YamlConfig) -> Self {
         let mut buttons: HashMap<String, ButtonType> = HashMap::new();
+        let long_press = yaml_config.modifiers.iter().any(|m| m.long_press);
         for yaml_button in &yaml_config.buttons {
+            let momentary = yaml_button.momentary.unwrap_or(false);
+            let modifier = yaml_config.modifiers.iter().find(|m| m.button == yaml_button.name).map(|m| m.mode);
+            // Own gestures, else the tap-or-hold of a momentary modifier, else the long press
+            // of `long_press` modifiers. Latching modifiers and `momentary` buttons get none.
+            let (gestures, layer_long_press) = match (&yaml_button.gestures, modifier) {
+                (Some(yaml_gestures), _) => (Some(Gestures::from_yaml(yaml_gestures)), false),
+                _ if momentary => (None, false),
+                (None, Some(YamlModifierMode::Momentary)) => (Some(Gestures::modifier()), false),
+                (None, Some(YamlModifierMode::Latching)) => (None, false),
+                (None, None) => (long_press.then(Gestures::layer_long_press), long_press),
+            };
+            let button = || Button {
+                curr: false,
+                prev: false,
+                read_i: yaml_button.read_i,
+                read_j: yaml_button.read_j.unwrap_or_default(),
+                write_idx: yaml_button.write_idx.unwrap_or(0),
+                midi_ctrl_ch: yaml_button.midi_ctrl_ch,
+                message: Message::from_yaml(yaml_button),
+                hotcue_ignore: yaml_button.hotcue_ignore.unwrap_or(false),
+                momentary,
+                rgb_led: yaml_button.rgb_led.unwrap_or(false),
+                gestures: gestures.clone(),
+                layer_long_press,
+                layers: layer_outputs(&yaml_button.layers),
+                debounce_count: 0,
+            };
             let button_type = match yaml_button.button_type {
-                YamlButtonType::Toggle => {
-                    let button = Button {
-                        curr: false,
-                        prev: false,
-                        read_i: yaml_button.read_i,
-                        read_j: yaml_button.read_j.unwrap(),
-                        write_idx: yaml_button.write_idx.unwrap_or(0),
-                        midi_ctrl_ch: yaml_button.midi_ctrl_ch,
-                        hotcue_ignore: yaml_button.hotcue_ignore.unwrap_or(false),
-                        debounce_count: 0,
-                        press_time: None,
-                    };
-                    ButtonType::Toggle(button)
-                }
-                YamlButtonType::Hold => {
-                    let button = Button {
-                        curr: false,
-                        prev: false,
-                        read_i: yaml_button.read_i,
-                        read_j: yaml_button.read_j.unwrap(),
-                        write_idx: yaml_button.write_idx.unwrap_or(0),
-                        midi_ctrl_ch: yaml_button.midi_ctrl_ch,
-                        hotcue_ignore: yaml_button.hotcue_ignore.unwrap_or(false),
-                        debounce_count: 0,
-                        press_time: None,
-                    };
-                    ButtonType::Hold(button)
-                }
-                YamlButtonType::Hotcue => {
-                    let button = Button {
-                        curr: false,
-                        prev: false,
-                        read_i: yaml_button.read_i,
-                        read_j: yaml_button.read_j.unwrap(),
-                        write_idx: yaml_button.write_idx.unwrap_or(0),
-                        midi_ctrl_ch: yaml_button.midi_ctrl_ch,
-                        hotcue_ignore: yaml_button.hotcue_ignore.unwrap_or(false),
-                        debounce_count: 0,
-                        press_time: None,
-                    };
-                    ButtonType::Hotcue(button)
-                }
+                YamlButtonType::Toggle => ButtonType::Toggle(button()),
+                YamlButtonType::Hold => ButtonType::Hold(button()),
+                YamlButtonType::Hotcue => ButtonType::Hotcue(button()),
                 YamlButtonType::Knob => {
                     let knob = Knob {
                         curr: 0,
                         prev: 0,
+                        fine: 0,
+                        response: Response::from_yaml(yaml_button),
+                        filter: KnobFilter::from_yaml(yaml_button),
+                        pickup: yaml_button.pickup.unwrap_or(false),
+                        pickup_led: yaml_button.pickup_led,
+                        pickup_wait: yaml_button.pickup_wait.unwrap_or(false),
                         read_i: yaml_button.read_i,
-                        read_j: yaml_button.read_j.unwrap(),
+                        read_j: yaml_button.read_j.unwrap_or_default(),
                         midi_ctrl_ch: yaml_button.midi_ctrl_ch,
+                        message: Message::from_yaml(yaml_button),
+                        layers: layer_outputs(&yaml_button.layers),
                     };
                     ButtonType::Knob(knob)
                 }
@@ -104,9 +168,12 @@ impl X1mk1Board {
                     let encoder = Encoder {
                         curr: 0,
                         prev: 0,
-                        read_pos: yaml_button.read_pos.unwrap(),
+                        read_pos: yaml_button.read_pos.unwrap_or('s'),
                         read_i: yaml_button.read_i,
                         midi_ctrl_ch: yaml_button.midi_ctrl_ch,
+                        message: Message::from_yaml(yaml_button),
+                        output: EncoderOutput::from_yaml(yaml_button),
+                        layers: layer_outputs(&yaml_button.layers),
                     };
                     ButtonType::Encoder(encoder)
                 }
@@ -114,7 +181,57 @@ impl X1mk1Board {
             buttons.insert(yaml_button.name.clone(), button_type);
         }
         X1mk1Board {
-            buttons
+            buttons,
+            profile_combo: yaml_config.profile_switch.clone(),
+        }
+    }
+
+    /// True while every `profile_switch` button is held.
+    pub(crate) fn profile_combo_held(&self) -> bool {
+        !self.profile_combo.is_empty()
+            && self.profile_combo.iter().all(|name| match self.buttons.get(name) {
+                Some(ButtonType::Toggle(b) | ButtonType::Hold(b) | ButtonType::Hotcue(b)) => b.curr,
+                _ => false,
+            })
+    }
+
+    /// Take over the runtime state (pressed, debounce, knob/encoder position) of every control that
+    /// kept its name, type and read position in `old`, so a board swapped in mid-session does not
+    /// see those controls as moved. Returns the controls that need a fresh baseline instead.
+    pub(crate) fn carry_state_from(&mut self, old: &X1mk1Board) -> Vec<String> {
+        let mut fresh = Vec::new();
+        for (name, control) in &mut self.buttons {
+            match (control, old.buttons.get(name)) {
+                (ButtonType::Toggle(new), Some(ButtonType::Toggle(old)))
+                | (ButtonType::Hold(new), Some(ButtonType::Hold(old)))
+                | (ButtonType::Hotcue(new), Some(ButtonType::Hotcue(old)))
+                    if new.read_i == old.read_i && new.read_j == old.read_j =>
+                {
+                    new.curr = old.curr;
+                    new.prev = old.prev;
+                    new.debounce_count = old.debounce_count;
+                    if let (Some(new), Some(old)) = (&mut new.gestures, &old.gestures) {
+                        new.carry_press_from(old);
+                    }
+                }
+                (ButtonType::Knob(new), Some(ButtonType::Knob(old)))
+                    if new.read_i == old.read_i && new.read_j == old.read_j =>
+                {
+                    new.curr = old.curr;
+                    new.prev = old.prev;
+                    new.fine = old.fine;
+                    new.filter.reset(old.filter.accepted());
+                }
+                (ButtonType::Encoder(new), Some(ButtonType::Encoder(old)))
+                    if new.read_i == old.read_i && new.read_pos == old.read_pos =>
+                {
+                    new.curr = old.curr;
+                    new.prev = old.prev;
+                    new.output.carry_from(&old.output);
+                }
+                _ => fresh.push(name.clone()),
+            }
         }
+        fresh
     }
 }
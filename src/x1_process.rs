@@ -1,107 +1,186 @@
 use std::sync::mpsc;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use midir::{MidiInput, MidiInputConnection, MidiOutput, MidiOutputConnection};
+use midir::{MidiInput, MidiInputConnection, MidiOutput};
 use midir::os::unix::{VirtualInput, VirtualOutput};
-use rusb::{Device, DeviceHandle, UsbContext};
 
-use crate::conf::YamlConfig;
-use crate::utils::{hex2bin, hex2bool, knob_to_midi};
+use crate::conf::{YamlConfig, YamlDevice, YamlModifierMode};
+use crate::decoder::{button_state, encoder_delta, encoder_position, ReportFormat};
+use crate::layers::{Layers, HOTCUE_LAYER};
+use crate::midi_out::{shift_channel_down, ChannelOffsetSink, MidiSink};
+use crate::pickup::Pickup;
+use crate::profiles::ConfigFollower;
+use crate::settings::Settings;
+use crate::transport::{Transport, TransportError};
 use crate::x1_board::{ButtonType, X1mk1Board};
 
-const USB_WRITE_FD: u8 = 0x01;
-const USB_UNLOCK_FD: u8 = 0x81;
-const USB_READ_FD: u8 = 0x84;
+const USB_TIMEOUT_MS: i32 = 50;
 const LED_DIM: u8 = 0x05;
 const LED_BRIGHT: u8 = 0x7F;
-const MIDI_CHANNEL: u8 = 0xB0;
 const MIDI_CHANNEL_LED: u8 = 0xB2;
 const MIDI_CHANNEL_HOTCUE: u8 = 0xB3;
+/// Channel of controls without their own `channel` (channel 1).
+const OUT_CHANNEL: u8 = 0;
+/// Channel of `Hotcue` controls without their own `channel` (channel 4).
+const HOTCUE_OUT_CHANNEL: u8 = 3;
 
-pub struct X1mk1<T: UsbContext> {
-    pub device: Device<T>,
-    pub handle: DeviceHandle<T>,
+pub struct X1mk1 {
+    pub transport: Box<dyn Transport>,
     pub serial_number: String,
-    midi_conn_out: MidiOutputConnection,
+    /// Name of the virtual MIDI input/output ports.
+    port_name: String,
+    midi_conn_out: ChannelOffsetSink,
     midi_conn_in: Option<MidiInputConnection<()>>,
     board: X1mk1Board,
+    /// Source of profile switches and hot-reloads.
+    config: Option<ConfigFollower>,
+    /// The `profile_switch` combo is down; it switches once per press.
+    profile_combo_held: bool,
+    /// Last complete report, the baseline for controls added by a reload.
+    prev_buf: Option<[u8; 24]>,
     usb_buffer: [u8; 24],
-    usb_timeout: Duration,
-    usb_endpoint: Endpoint,
+    usb_timeout: i32,
     led: [u8; 32],
     led_hotcue: [u8; 16],
+    /// `led_brightness` of the device config; scales every LED value written.
+    led_brightness: u8,
     led_debug: bool,
-    out_endpoints: Vec<u8>,
-    shift: u8,
-    hotcue: bool,
+    led_only: bool,
+    layers: Layers,
+    pickup: Pickup,
     run_flag: std::sync::Arc<AtomicBool>,
 }
 
-struct Endpoint {
-    config: u8,
-    interface: u8,
-    setting: u8,
-    address: u8,
-}
-
-impl<T: UsbContext> X1mk1<T> {
-    pub fn new(device: Device<T>, handle: DeviceHandle<T>, serial_number: String, yaml_config: YamlConfig, run_flag: std::sync::Arc<AtomicBool>) -> Self {
-        const MIDI_PORT_NAME: &str = "EASY KONTROL X1";
+impl X1mk1 {
+    pub fn new(
+        transport: Box<dyn Transport>,
+        serial_number: String,
+        port_name: String,
+        yaml_config: YamlConfig,
+        settings: &Settings,
+        run_flag: std::sync::Arc<AtomicBool>,
+    ) -> Self {
         let midi_out = MidiOutput::new("Easy KONTROL X1").unwrap();
-        let midi_conn_out = midi_out.create_virtual(MIDI_PORT_NAME).unwrap();
+        let midi_conn_out = midi_out.create_virtual(&port_name).unwrap();
+        Self::with_output(transport, Box::new(midi_conn_out), serial_number, port_name, yaml_config, settings, run_flag)
+    }
+
+    /// Build a handler that sends its MIDI to `midi_conn_out` instead of a new virtual port.
+    pub fn with_output(
+        transport: Box<dyn Transport>,
+        midi_conn_out: Box<dyn MidiSink>,
+        serial_number: String,
+        port_name: String,
+        yaml_config: YamlConfig,
+        settings: &Settings,
+        run_flag: std::sync::Arc<AtomicBool>,
+    ) -> Self {
         let board = X1mk1Board::from_yaml(&yaml_config);
+        let layers = Layers::from_yaml(&yaml_config);
         let mut leds = [0x05; 32];
         let led_hotcue = [0x05; 16];
         leds[0] = 0x0C;
         leds[31] = 0;
         let usb_buffer = [0; 24];
-        let usb_endpoint = Endpoint {
-            address: USB_READ_FD,
-            config: 1,
-            interface: 0,
-            setting: 0,
-        };
-        let led_debug = std::env::var("LED_DEBUG").ok().as_deref() == Some("1");
-
         Self {
-            device,
-            handle,
+            transport,
             serial_number,
-            midi_conn_out,
+            port_name,
+            midi_conn_out: ChannelOffsetSink::new(midi_conn_out, 0),
             midi_conn_in: None,
             board,
+            config: None,
+            profile_combo_held: false,
+            prev_buf: None,
             usb_buffer,
-            usb_timeout: Duration::from_millis(50),
-            usb_endpoint,
+            usb_timeout: USB_TIMEOUT_MS,
             led: leds,
             led_hotcue,
-            led_debug,
-            out_endpoints: vec![USB_WRITE_FD],
-            shift: 0,
-            hotcue: false,
+            led_brightness: LED_BRIGHT,
+            led_debug: settings.led_debug,
+            led_only: settings.led_only,
+            layers,
+            pickup: Pickup::default(),
             run_flag,
         }
     }
 
+    /// Switch mappings when the device's profile changes or its file is edited.
+    pub fn follow_config(&mut self, follower: ConfigFollower) {
+        self.config = Some(follower);
+    }
+
+    fn check_config(&mut self) {
+        let Some(follower) = &mut self.config else { return };
+        let combo_held = self.board.profile_combo_held();
+        if combo_held && !self.profile_combo_held {
+            follower.cycle();
+        }
+        self.profile_combo_held = combo_held;
+        let reloaded = follower.poll();
+        let device = follower.poll_device();
+        if let Some(yaml_config) = reloaded {
+            self.apply_config(&yaml_config);
+            println!("✓ {} switched mapping", self.serial_number);
+        }
+        if let Some(device) = device {
+            self.use_device_config(&device);
+        }
+    }
+
+    /// Swap in a new mapping mid-session; see [`crate::x1_process_hid::X1mk1Hid::apply_config`].
+    fn apply_config(&mut self, yaml_config: &YamlConfig) {
+        let mut board = X1mk1Board::from_yaml(yaml_config);
+        let fresh = board.carry_state_from(&self.board);
+        if let Some(buf) = self.prev_buf {
+            for name in fresh {
+                match board.buttons.get_mut(&name) {
+                    Some(ButtonType::Toggle(b) | ButtonType::Hold(b) | ButtonType::Hotcue(b)) => {
+                        b.curr = button_state(&buf, ReportFormat::Usb, b.read_i, b.read_j);
+                        b.prev = b.curr;
+                    }
+                    Some(ButtonType::Knob(k)) => {
+                        let position = k.reset(&buf);
+                        k.curr = k.response.value(position);
+                        k.prev = k.curr;
+                        k.fine = k.response.value14(position);
+                    }
+                    Some(ButtonType::Encoder(e)) => {
+                        e.curr = encoder_position(&buf, e.read_i, e.read_pos);
+                        e.prev = e.curr;
+                    }
+                    None => {}
+                }
+            }
+        }
+        self.layers.replace_modifiers(yaml_config);
+        self.board = board;
+    }
+
+    /// Apply the controller's `devices:` entry; fields it leaves out go back to their defaults.
+    pub fn use_device_config(&mut self, device: &YamlDevice) {
+        self.led_brightness = device.led_brightness.unwrap_or(LED_BRIGHT).min(LED_BRIGHT);
+        self.midi_conn_out.offset = device.channel_offset.unwrap_or(0);
+    }
+
     pub(crate) fn init(&mut self, sender: mpsc::Sender<Vec<u8>>) {
-        const MIDI_PORT_NAME: &str = "EASY KONTROL X1";
         let midi_in = MidiInput::new("Easy KONTROL X1").unwrap();
         let midi_conn_in = midi_in.create_virtual(
-            MIDI_PORT_NAME,
+            &self.port_name,
             move |_stamp, message: &[u8], _| {
                 sender.send(message.to_vec()).unwrap();
             }, ()).unwrap();
         self.midi_conn_in = Some(midi_conn_in); // Prevents the connection from being dropped
     }
 
-    pub(crate) fn read(&mut self) -> rusb::Result<()> {
+    pub(crate) fn read(&mut self) -> Result<(), TransportError> {
         println!("Reading from device");
         let (midi_tx, midi_rx) = mpsc::channel::<Vec<u8>>();
-        let led_only = std::env::var("LED_ONLY").ok().as_deref() == Some("1");
+        let led_only = self.led_only;
 
         self.init(midi_tx);
-        if let Err(e) = self.configure_endpoint() {
+        if let Err(e) = self.transport.configure() {
             if self.led_debug {
                 eprintln!("[USB] configure_endpoint failed: {:?}", e);
             }
@@ -115,8 +194,14 @@ impl<T: UsbContext> X1mk1<T> {
                 std::thread::sleep(Duration::from_millis(100));
                 continue;
             }
+            self.check_config();
             match midi_rx.try_recv() {
                 Ok(message) => {
+                    let Some(message) = shift_channel_down(&message, self.midi_conn_out.offset) else { continue };
+                    if message.len() < 3 {
+                        continue;
+                    }
+                    self.pickup.echo(&message);
                     let i = message[1] as usize;
                     if (0..32).contains(&i) {
                         if message[0] == MIDI_CHANNEL_LED {
@@ -132,18 +217,20 @@ impl<T: UsbContext> X1mk1<T> {
                 Err(_) => {}
             }
             if !led_only {
-                match self.handle.read_bulk(self.usb_endpoint.address, &mut self.usb_buffer, self.usb_timeout) {
+                match self.transport.read_report(&mut self.usb_buffer, self.usb_timeout) {
                     Ok(len) => {
                         if len != self.usb_buffer.len() {
                             // rusb crate consider partially read data as ok but we do not.
                             continue;
                         }
                         self.read_state(self.usb_buffer);
+                        self.prev_buf = Some(self.usb_buffer);
                     }
                     Err(e) => {
-                        if e == rusb::Error::Timeout {
+                        if e == TransportError::Timeout {
                             // Weird timeout occurring when all knobs are at 0 position and no button is pressed.
                             // We do not want to break because there's no need to call configure_endpoint again.
+                            self.tick_gestures(Instant::now());
                             continue;
                         }
                         return Err(e);
@@ -156,187 +243,156 @@ impl<T: UsbContext> X1mk1<T> {
         }
     }
 
-    fn configure_endpoint(&mut self) -> rusb::Result<()> {
-        if let Err(e) = self.handle.set_auto_detach_kernel_driver(true) {
-            if self.led_debug {
-                eprintln!("[USB] auto_detach_kernel_driver failed: {:?}", e);
-            }
-        }
-        if let Err(e) = self.handle.set_active_configuration(self.usb_endpoint.config) {
-            if self.led_debug {
-                eprintln!("[USB] set_active_configuration failed: {:?}", e);
-            }
-            return Err(e);
-        }
-        // Try without claiming interface first - the kernel HID driver might handle this
-        match self.handle.claim_interface(self.usb_endpoint.interface) {
-            Ok(_) => println!("Successfully claimed interface"),
-            Err(e) => {
-                println!("Could not claim interface (continuing anyway): {:?}", e);
-                // Try detach across all interfaces
-                if let Ok(config) = self.device.active_config_descriptor() {
-                    for interface in config.interfaces() {
-                        let iface = interface.number();
-                        if let Err(e2) = self.handle.detach_kernel_driver(iface) {
-                            if self.led_debug {
-                                eprintln!("[USB] detach_kernel_driver iface={} failed: {:?}", iface, e2);
-                            }
-                        } else if self.led_debug {
-                            eprintln!("[USB] detached kernel driver on iface={}", iface);
-                        }
-                    }
-                }
-                let _ = self.handle.claim_interface(self.usb_endpoint.interface);
-            }
-        }
-        if let Err(e) = self.handle.set_alternate_setting(self.usb_endpoint.interface, self.usb_endpoint.setting) {
-            if self.led_debug {
-                eprintln!("[USB] set_alternate_setting failed: {:?}", e);
-            }
-            return Err(e);
-        }
-        if let Ok(config) = self.device.active_config_descriptor() {
-            let mut outs = Vec::new();
-            for interface in config.interfaces() {
-                for descriptor in interface.descriptors() {
-                    for ep in descriptor.endpoint_descriptors() {
-                        let addr = ep.address();
-                        if ep.direction() == rusb::Direction::Out {
-                            outs.push(addr);
-                        }
-                        if self.led_debug {
-                            eprintln!(
-                                "[USB EP] iface={} alt={} addr=0x{:02x} dir={:?} xfer={:?} max_packet={}",
-                                descriptor.interface_number(),
-                                descriptor.setting_number(),
-                                addr,
-                                ep.direction(),
-                                ep.transfer_type(),
-                                ep.max_packet_size()
-                            );
-                        }
-                    }
-                }
-            }
-            if !outs.is_empty() {
-                outs.sort();
-                outs.dedup();
-                self.out_endpoints = outs;
-            }
-            if self.led_debug {
-                eprintln!("[USB EP] out_endpoints={:02x?}", self.out_endpoints);
-            }
-        }
-        Ok(())
-    }
-
     fn read_state(&mut self, buf: [u8; 24]) {
-        let mut binbyte: [[bool; 8]; 5] = [[false; 8]; 5];
-
-        hex2bool(buf[1], &mut binbyte[0]);
-        hex2bool(buf[2], &mut binbyte[1]);
-        hex2bool(buf[3], &mut binbyte[2]);
-        hex2bool(buf[4], &mut binbyte[3]);
-        hex2bool(buf[5], &mut binbyte[4]);
-
+        let now = Instant::now();
+        let hotcue = self.layers.is_active(HOTCUE_LAYER);
         for (ctrl_name, button_type) in &mut self.board.buttons {
             match button_type {
                 ButtonType::Toggle(ref mut button) => {
-                    if self.hotcue && button.hotcue_ignore {
+                    if hotcue && button.hotcue_ignore {
                         continue;
                     }
-                    button.curr = binbyte[button.read_i as usize][button.read_j as usize];
+                    button.curr = button_state(&buf, ReportFormat::Usb, button.read_i, button.read_j);
                     if button.curr != button.prev {
-                        if button.curr {
-                            let _l = self.led[button.write_idx as usize];
-                            let _ = self.midi_conn_out.send(&[MIDI_CHANNEL + self.shift, button.midi_ctrl_ch, 127]);
-                            if ctrl_name.eq("HOTCUE") {
-                                self.hotcue = !self.hotcue;
-                                self.led[button.write_idx as usize] = if self.hotcue { LED_BRIGHT } else { LED_DIM };
+                        let latching = self.layers.modifier(ctrl_name).map(|m| m.mode) == Some(YamlModifierMode::Latching);
+                        let released = if button.curr {
+                            self.layers.press(ctrl_name);
+                            None
+                        } else {
+                            self.layers.release(ctrl_name)
+                        };
+                        let pressed = button.curr;
+                        if let Some(gestures) = button.recognizer(&self.layers, pressed) {
+                            let fired = if pressed { gestures.press(now) } else { gestures.release(now) };
+                            self.midi_conn_out.send_all(button.gesture_messages(&self.layers, OUT_CHANNEL, &fired));
+                        } else if button.curr && !latching {
+                            let channel = button.message.channel.unwrap_or(OUT_CHANNEL);
+                            let (channel, number) = self.layers.output(&button.layers, channel, button.midi_ctrl_ch);
+                            self.midi_conn_out.send_all(button.message.button(channel, number, true));
+                        }
+                        if let Some((YamlModifierMode::Latching, on)) = released {
+                            // The MK1 frame has 32 LEDs; higher indices are MK2-only.
+                            if let Some(led) = self.led.get_mut(button.write_idx as usize) {
+                                *led = if on { LED_BRIGHT } else { LED_DIM };
                             }
                         }
                     }
                     button.prev = button.curr;
                 }
                 ButtonType::Hold(ref mut button) => {
-                    if self.hotcue && button.hotcue_ignore {
+                    if hotcue && button.hotcue_ignore {
                         continue;
                     }
-                    button.curr = binbyte[button.read_i as usize][button.read_j as usize];
+                    button.curr = button_state(&buf, ReportFormat::Usb, button.read_i, button.read_j);
                     if button.curr == button.prev {
                         continue;
-                    } else if button.curr {
-                        let _ = self.midi_conn_out.send(&[MIDI_CHANNEL + self.shift, button.midi_ctrl_ch, 127]);
-                        if ctrl_name.eq("SHIFT") {
-                            self.shift = 1;
-                        }
+                    }
+                    let latching = self.layers.modifier(ctrl_name).map(|m| m.mode) == Some(YamlModifierMode::Latching);
+                    if button.curr {
+                        self.layers.press(ctrl_name);
                     } else {
-                        self.led[button.write_idx as usize] = LED_DIM;
-                        if ctrl_name.eq("SHIFT") {
-                            self.shift = 0;
+                        let on = self.layers.release(ctrl_name).is_some_and(|(_, on)| on);
+                        if let Some(led) = self.led.get_mut(button.write_idx as usize) {
+                            *led = if on { LED_BRIGHT } else { LED_DIM };
                         }
-                        let _ = self.midi_conn_out.send(&[MIDI_CHANNEL + self.shift, button.midi_ctrl_ch, 0]);
+                    }
+                    let pressed = button.curr;
+                    if let Some(gestures) = button.recognizer(&self.layers, pressed) {
+                        let fired = if pressed { gestures.press(now) } else { gestures.release(now) };
+                        self.midi_conn_out.send_all(button.gesture_messages(&self.layers, OUT_CHANNEL, &fired));
+                    } else if !latching {
+                        let channel = button.message.channel.unwrap_or(OUT_CHANNEL);
+                        let (channel, number) = self.layers.output(&button.layers, channel, button.midi_ctrl_ch);
+                        self.midi_conn_out.send_all(button.message.button(channel, number, button.curr));
                     }
                     button.prev = button.curr;
                 }
                 ButtonType::Hotcue(ref mut button) => {
-                    if !self.hotcue {
+                    if !hotcue {
                         continue;
                     }
-                    button.curr = binbyte[button.read_i as usize][button.read_j as usize];
+                    button.curr = button_state(&buf, ReportFormat::Usb, button.read_i, button.read_j);
                     if button.curr == button.prev {
                         continue;
-                    } else if button.curr {
-                        let _ = self.midi_conn_out.send(&[MIDI_CHANNEL_HOTCUE + self.shift, button.midi_ctrl_ch, 127]);
+                    }
+                    let pressed = button.curr;
+                    if let Some(gestures) = button.recognizer(&self.layers, pressed) {
+                        let fired = if pressed { gestures.press(now) } else { gestures.release(now) };
+                        self.midi_conn_out.send_all(button.gesture_messages(&self.layers, HOTCUE_OUT_CHANNEL, &fired));
                     } else {
-                        let _ = self.midi_conn_out.send(&[MIDI_CHANNEL_HOTCUE + self.shift, button.midi_ctrl_ch, 0]);
+                        let channel = button.message.channel.unwrap_or(HOTCUE_OUT_CHANNEL);
+                        let (channel, number) = self.layers.output(&button.layers, channel, button.midi_ctrl_ch);
+                        self.midi_conn_out.send_all(button.message.button(channel, number, button.curr));
                     }
                     button.prev = button.curr;
                 }
                 ButtonType::Knob(ref mut knob) => {
-                    knob.curr = knob_to_midi(buf[knob.read_i as usize], buf[knob.read_j as usize]);
-                    if knob.curr != knob.prev {
-                        let _ = self.midi_conn_out.send(&[MIDI_CHANNEL + self.shift, knob.midi_ctrl_ch, knob.curr]);
+                    let position = knob.position(&buf);
+                    knob.curr = knob.response.value(position);
+                    let fine = knob.response.value14(position);
+                    let moved = if knob.message.high_res { fine != knob.fine } else { knob.curr != knob.prev };
+                    let channel = knob.message.channel.unwrap_or(OUT_CHANNEL);
+                    let (channel, number) = self.layers.output(&knob.layers, channel, knob.midi_ctrl_ch);
+                    if moved {
+                        if knob.pickup && !self.pickup.take(channel, number, knob.prev, knob.curr, knob.pickup_wait) {
+                            // Not picked up yet; the target keeps its value.
+                        } else if knob.message.high_res {
+                            self.midi_conn_out.send_all(knob.message.value14(channel, number, fine));
+                        } else {
+                            self.midi_conn_out.send_all(knob.message.value(channel, number, knob.curr));
+                        }
+                    }
+                    if let Some(idx) = knob.pickup_led.filter(|&idx| idx < 32) {
+                        let waiting = self.pickup.waiting(channel, number, knob.curr, knob.pickup_wait);
+                        self.led[idx as usize] = if waiting { LED_BRIGHT } else { LED_DIM };
                     }
                     knob.prev = knob.curr;
+                    knob.fine = fine;
                 }
                 ButtonType::Encoder(ref mut encoder) => {
-                    let mut binnum = [0; 8];
-                    hex2bin(buf[encoder.read_i as usize], &mut binnum);
-                    match encoder.read_pos {
-                        's' => {
-                            encoder.curr = binnum[0] + binnum[1] * 2 + binnum[2] * 4 + binnum[3] * 8;
-                        }
-                        'e' => {
-                            encoder.curr = binnum[4] + binnum[5] * 2 + binnum[6] * 4 + binnum[7] * 8;
-                        }
-                        _ => panic!("Invalid read_pos"),
-                    }
+                    encoder.curr = encoder_position(&buf, encoder.read_i, encoder.read_pos);
                     if encoder.curr != encoder.prev {
-                        // Clockwise init
-                        let mut velocity = 1;
-                        if (encoder.prev == 15 && encoder.curr == 0) || (encoder.prev == 0 && encoder.curr == 15) {
-                            // Full rotation special case
-                            velocity = if encoder.prev == 15 { 1 } else { 127 };
-                        } else if encoder.curr < encoder.prev {
-                            // Clockwise
-                            velocity = 127;
-                        }
-                        let _ = self.midi_conn_out.send(&[MIDI_CHANNEL + self.shift, encoder.midi_ctrl_ch, velocity]);
+                        let delta = encoder_delta(encoder.prev, encoder.curr);
+                        let channel = encoder.message.channel.unwrap_or(OUT_CHANNEL);
+                        let (channel, number) = self.layers.output(&encoder.layers, channel, encoder.midi_ctrl_ch);
+                        self.midi_conn_out.send_all(encoder.output.turn(&encoder.message, channel, number, delta, now));
                     }
                     encoder.prev = encoder.curr;
                 }
             }
         }
+        self.tick_gestures(now);
     }
 
-    fn update_leds(&self) {
+    /// Send what button gestures decided by time alone; see [`crate::x1_process_hid::X1mk1Hid`].
+    fn tick_gestures(&mut self, now: Instant) {
+        let hotcue = self.layers.is_active(HOTCUE_LAYER);
+        let mut messages = Vec::new();
+        for button_type in self.board.buttons.values_mut() {
+            let (button, channel) = match button_type {
+                ButtonType::Toggle(b) | ButtonType::Hold(b) if !(hotcue && b.hotcue_ignore) => (b, OUT_CHANNEL),
+                ButtonType::Hotcue(b) if hotcue => (b, HOTCUE_OUT_CHANNEL),
+                _ => continue,
+            };
+            let Some(gestures) = &mut button.gestures else { continue };
+            let fired = gestures.tick(now);
+            messages.extend(button.gesture_messages(&self.layers, channel, &fired));
+        }
+        self.midi_conn_out.send_all(messages);
+    }
+
+    fn update_leds(&mut self) {
         let mut led = self.led;
-        if self.hotcue {
+        if self.layers.is_active(HOTCUE_LAYER) {
             for i in 9..25 {
                 led[i] = self.led_hotcue[i - 9];
             }
         }
+        if self.led_brightness != LED_BRIGHT {
+            for v in &mut led {
+                *v = (*v as u16 * self.led_brightness as u16 / LED_BRIGHT as u16) as u8;
+            }
+        }
         let mut payloads: Vec<Vec<u8>> = Vec::new();
         payloads.push(led.to_vec());
         let mut buf0 = vec![0u8; 33];
@@ -348,29 +404,63 @@ impl<T: UsbContext> X1mk1<T> {
         buf1[1..].copy_from_slice(&led);
         payloads.push(buf1);
 
-        for ep in &self.out_endpoints {
-            for payload in &payloads {
-                match self.handle.write_bulk(*ep, payload, self.usb_timeout) {
-                    Ok(n) => {
-                        if self.led_debug {
-                            eprintln!("[LED BULK] ok ep=0x{:02x} len={} wrote={}", ep, payload.len(), n);
-                        }
-                    }
-                    Err(e) => {
-                        if self.led_debug {
-                            eprintln!("[LED BULK] err ep=0x{:02x} len={} err={:?}", ep, payload.len(), e);
-                        }
-                    }
-                }
-            }
+        for payload in &payloads {
+            // Errors are logged per endpoint by the transport with --led-debug (or LED_DEBUG set).
+            let _ = self.transport.write_report(payload);
         }
-        match self.handle.read_bulk(USB_UNLOCK_FD, &mut [0; 1], self.usb_timeout) {
-            Ok(_) => {}
-            Err(e) => {
-                if e != rusb::Error::Timeout {
-                    // Silently ignore non-timeout errors for now
-                }
-            }
+        self.transport.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::midi_out::RecordingSink;
+    use crate::transport::MockTransport;
+    use crate::validate::load_config_file;
+
+    /// SHIFT and HOTCUE are latching modifiers here too: they send nothing, HOTCUE mutes the
+    /// `hotcue_ignore` buttons and SHIFT moves buttons to channel 2, sent on release.
+    #[test]
+    fn latching_modifiers() {
+        let config = load_config_file(&Path::new(env!("CARGO_MANIFEST_DIR")).join("board.yml")).unwrap();
+        let sink = RecordingSink::new();
+        let mut x1 = X1mk1::with_output(
+            Box::new(MockTransport::new()),
+            Box::new(sink.clone()),
+            "TEST".to_string(),
+            "TEST".to_string(),
+            config,
+            &Settings::default(),
+            Arc::new(AtomicBool::new(true)),
+        );
+        let mut report = [0u8; 24];
+        let mut tap = |x1: &mut X1mk1, byte: usize, bit: u8| {
+            report[byte] |= bit;
+            x1.read_state(report);
+            report[byte] &= !bit;
+            x1.read_state(report);
         };
+        let (hotcue, shift, sync) = ((5, 0x80), (21, 0x04), (23, 0x04));
+
+        tap(&mut x1, sync.0, sync.1);
+        assert_eq!(sink.take(), vec![vec![0xB0, 42, 127], vec![0xB0, 42, 0]]);
+
+        // The HOTCUE bit shares its byte with a knob reading, so only look for the modifier CCs.
+        let sends = |sink: &RecordingSink, cc: u8| sink.take().iter().any(|m| m[1] == cc);
+        tap(&mut x1, hotcue.0, hotcue.1);
+        assert!(!sends(&sink, 46));
+        tap(&mut x1, sync.0, sync.1);
+        assert!(sink.take().is_empty());
+        tap(&mut x1, hotcue.0, hotcue.1);
+        assert!(!sends(&sink, 46));
+
+        tap(&mut x1, shift.0, shift.1);
+        assert!(sink.take().is_empty());
+        tap(&mut x1, sync.0, sync.1);
+        assert_eq!(sink.take(), vec![vec![0xB1, 42, 127], vec![0xB1, 42, 0]]);
     }
 }
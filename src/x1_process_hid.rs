@@ -1,38 +1,46 @@
-use midir::{MidiInput, MidiInputConnection, MidiOutput, MidiOutputConnection};
+use midir::{MidiInput, MidiInputConnection, MidiOutput};
 use midir::os::unix::{VirtualInput, VirtualOutput};
 use std::collections::HashSet;
 use std::sync::mpsc;
 
-use crate::conf::YamlConfig;
-use crate::utils::{hex2bin, knob_to_midi};
+use crate::conf::{YamlConfig, YamlDevice, YamlModifierMode};
+use crate::decoder::{button_state, encoder_delta, encoder_position, hid_payload, ReportFormat};
+use crate::layers::{Layers, HOTCUE_LAYER};
+use crate::merged_port::MergedPort;
+use crate::midi_out::{shift_channel_down, ChannelOffsetSink, MidiSink};
+use crate::pickup::Pickup;
+use crate::profiles::ConfigFollower;
+use crate::session::SessionState;
+use crate::settings::Settings;
+use crate::transport::{Transport, TransportError};
 use crate::x1_board::{ButtonType, X1mk1Board};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{Duration, Instant};
 
-const USB_WRITE_FD: u8 = 0x01;
 const LED_DIM: u8 = 0x00;
 const LED_BRIGHT: u8 = 0x7F;
-const MIDI_CHANNEL: u8 = 0xB0;
+/// Channel of controls without their own `channel` (channel 1).
+const OUT_CHANNEL: u8 = 0;
 const MIDI_CHANNEL_LED: u8 = 0xB2;
 const MIDI_CHANNEL_HOTCUE: u8 = 0xB0;
-const MIDI_CHANNEL_FX_HOLD: u8 = 0xB2;
-const LONG_PRESS_MS: u64 = 800;
-const LONG_PRESS_OFFSET: u8 = 32;
-const FX_HOLD_MASK_FX1_PLAY: u8 = 1 << 0;
-const FX_HOLD_MASK_FX1_1: u8 = 1 << 1;
-const FX_HOLD_MASK_FX1_2: u8 = 1 << 2;
-const FX_HOLD_MASK_FX1_3: u8 = 1 << 3;
-const FX_HOLD_MASK_FX2_PLAY: u8 = 1 << 4;
-const FX_HOLD_MASK_FX2_1: u8 = 1 << 5;
-const FX_HOLD_MASK_FX2_2: u8 = 1 << 6;
-const FX_HOLD_MASK_FX2_3: u8 = 1 << 7;
+
+/// CC to LED index map, RGB pad LED indices and the LEDs of latching modifiers.
+type LedLookup = (std::collections::HashMap<u8, u8>, HashSet<u8>, HashSet<u8>);
 
 pub struct X1mk1Hid {
-    pub handle: hidapi::HidDevice,
+    pub transport: Box<dyn Transport>,
     pub serial_number: String,
-    midi_conn_out: MidiOutputConnection,
+    /// Name of the virtual MIDI input/output ports.
+    port_name: String,
+    midi_conn_out: ChannelOffsetSink,
     midi_conn_in: Option<MidiInputConnection<()>>,
+    /// Shared port and channel slot when running in merged-port mode.
+    merged_input: Option<(std::sync::Arc<MergedPort>, u8)>,
     board: X1mk1Board,
+    /// Source of profile switches and hot-reloads.
+    config: Option<ConfigFollower>,
+    /// The `profile_switch` combo is down; it switches once per press.
+    profile_combo_held: bool,
     usb_buffer: [u8; 65],
     usb_timeout: i32,
     led: [u8; 32],
@@ -40,8 +48,10 @@ pub struct X1mk1Hid {
     led_ext: [u8; 58],
     led_bank80: [u8; 51],
     led_bank81: [u8; 90],
+    /// `led_brightness` of the device config; scales every LED value written.
+    led_brightness: u8,
     led_map: std::collections::HashMap<u8, u8>,
-    hotcue_leds: HashSet<u8>,
+    rgb_leds: HashSet<u8>,
     led_report_id: u8,
     led_test: bool,
     led_test_idx: u8,
@@ -55,74 +65,72 @@ pub struct X1mk1Hid {
     led_once: bool,
     log_byte_changes: bool,
     led_rid: Option<u8>,
-    shift_led_idx: Option<u8>,
-    shift: u8,
-    fx_hold_mask: u8,
-    hotcue: bool,
+    /// LEDs of latching modifiers; they show the layer state and ignore MIDI input.
+    modifier_leds: HashSet<u8>,
+    layers: Layers,
+    pickup: Pickup,
     initialized: bool,  // Flag: true after first stable read
     prev_buf: [u8; 64],
     encoder_quiet_count: u8,
+    /// Knob values from a previous session, compared with the first report after a reconnect.
+    restored_knobs: std::collections::HashMap<String, u8>,
     run_flag: std::sync::Arc<AtomicBool>,
 }
 
 impl X1mk1Hid {
-    pub fn new(handle: hidapi::HidDevice, serial_number: String, yaml_config: YamlConfig, run_flag: std::sync::Arc<AtomicBool>) -> Self {
+    pub fn new(
+        transport: Box<dyn Transport>,
+        serial_number: String,
+        port_name: String,
+        yaml_config: YamlConfig,
+        settings: &Settings,
+        run_flag: std::sync::Arc<AtomicBool>,
+    ) -> Self {
         println!("Creating MIDI ports for device");
-        const MIDI_PORT_NAME: &str = "EASY KONTROL X1";
         let midi_out = MidiOutput::new("Easy KONTROL X1").unwrap();
-        let midi_conn_out = midi_out.create_virtual(MIDI_PORT_NAME).unwrap();
-        println!("✓ Created virtual MIDI output port: {}", MIDI_PORT_NAME);
+        let midi_conn_out = midi_out.create_virtual(&port_name).unwrap();
+        println!("✓ Created virtual MIDI output port: {}", port_name);
+        Self::with_output(transport, Box::new(midi_conn_out), serial_number, port_name, yaml_config, settings, run_flag)
+    }
+
+    /// Build a handler that sends its MIDI to `midi_conn_out` instead of a new virtual port.
+    pub fn with_output(
+        transport: Box<dyn Transport>,
+        midi_conn_out: Box<dyn MidiSink>,
+        serial_number: String,
+        port_name: String,
+        yaml_config: YamlConfig,
+        settings: &Settings,
+        run_flag: std::sync::Arc<AtomicBool>,
+    ) -> Self {
         let board = X1mk1Board::from_yaml(&yaml_config);
-        let mut led_map = std::collections::HashMap::new();
-        let mut hotcue_leds = HashSet::new();
-        let mut shift_led_idx: Option<u8> = None;
-        for (name, button_type) in &board.buttons {
-            match button_type {
-                ButtonType::Toggle(b) | ButtonType::Hold(b) | ButtonType::Hotcue(b) => {
-                    led_map.insert(b.midi_ctrl_ch, b.write_idx);
-                    if name.starts_with("DECK_A_HOTCUE") || name.starts_with("DECK_B_HOTCUE") {
-                        hotcue_leds.insert(b.write_idx);
-                    }
-                }
-                _ => {}
-            }
-        }
-        for (name, button_type) in &board.buttons {
-            if name.as_str() == "SHIFT" {
-                if let ButtonType::Hold(b) | ButtonType::Toggle(b) | ButtonType::Hotcue(b) = button_type {
-                    shift_led_idx = Some(b.write_idx);
-                }
-            }
-        }
+        let layers = Layers::from_yaml(&yaml_config);
+        let (led_map, rgb_leds, modifier_leds) = Self::led_lookup(&board, &layers);
         let mut leds = [0x05; 32];
         let led_hotcue = [0x05; 16];
         leds.fill(LED_DIM);
         let usb_buffer = [0; 65];
-        let led_test = std::env::var("LED_TEST").ok().as_deref() == Some("1");
-        let led_debug = std::env::var("LED_DEBUG").ok().as_deref() == Some("1");
-        let led_all = std::env::var("LED_ALL").ok().as_deref() == Some("1");
-        let led_clear = std::env::var("LED_CLEAR").ok().as_deref() == Some("1");
-        let led_ext_enabled = std::env::var("LED_EXT").ok().as_deref() == Some("1");
-        let log_byte_changes = std::env::var("LOG_BYTES").ok().as_deref() == Some("1");
-        let led_bank = std::env::var("LED_BANK")
-            .ok()
-            .and_then(|s| u8::from_str_radix(s.trim_start_matches("0x"), 16).ok())
-            .unwrap_or(0x80);
-        let led_idx = std::env::var("LED_IDX")
-            .ok()
-            .and_then(|s| s.parse::<usize>().ok());
-        let led_once = std::env::var("LED_ONCE").ok().as_deref() == Some("1");
-        let led_rid = std::env::var("LED_RID")
-            .ok()
-            .and_then(|s| u8::from_str_radix(s.trim_start_matches("0x"), 16).ok())
-            .or(Some(0x80));
+        let led_test = settings.led_test.chase;
+        let led_debug = settings.led_debug;
+        let led_all = settings.led_test.all;
+        let led_clear = settings.led_test.clear;
+        let led_ext_enabled = settings.led_test.ext;
+        let log_byte_changes = settings.log_bytes;
+        let led_bank = settings.led_test.bank;
+        let led_idx = settings.led_test.idx;
+        let led_once = settings.led_test.once;
+        let led_rid = settings.led_test.rid;
 
         Self {
-            handle,
+            transport,
             serial_number,
-            midi_conn_out,
+            port_name,
+            midi_conn_out: ChannelOffsetSink::new(midi_conn_out, 0),
             midi_conn_in: None,
+            merged_input: None,
             board,
+            config: None,
+            profile_combo_held: false,
             usb_buffer,
             usb_timeout: 50,
             led: leds,
@@ -130,8 +138,9 @@ impl X1mk1Hid {
             led_ext: [0; 58],
             led_bank80: [0; 51],
             led_bank81: [0; 90],
+            led_brightness: LED_BRIGHT,
             led_map,
-            hotcue_leds,
+            rgb_leds,
             led_report_id: 0,
             led_test,
             led_test_idx: 0,
@@ -145,47 +154,140 @@ impl X1mk1Hid {
             led_bank,
             log_byte_changes,
             led_rid,
-            shift_led_idx,
-            shift: 0,
-            fx_hold_mask: 0,
-            hotcue: false,
+            modifier_leds,
+            layers,
+            pickup: Pickup::default(),
             initialized: false,  // Not yet initialized
             prev_buf: [0; 64],
             encoder_quiet_count: 0,
+            restored_knobs: std::collections::HashMap::new(),
             run_flag,
         }
     }
 
-    fn fx_hold_bit(cc: u8) -> Option<u8> {
-        match cc {
-            8 => Some(FX_HOLD_MASK_FX1_PLAY),
-            10 => Some(FX_HOLD_MASK_FX1_1),
-            12 => Some(FX_HOLD_MASK_FX1_2),
-            14 => Some(FX_HOLD_MASK_FX1_3),
-            9 => Some(FX_HOLD_MASK_FX2_PLAY),
-            11 => Some(FX_HOLD_MASK_FX2_1),
-            13 => Some(FX_HOLD_MASK_FX2_2),
-            15 => Some(FX_HOLD_MASK_FX2_3),
-            _ => None,
+    /// Current LED frames, latched modes and knob values, to be handed to [`Self::restore`] on reconnect.
+    pub fn snapshot(&self) -> SessionState {
+        let knobs = self
+            .board
+            .buttons
+            .iter()
+            .filter_map(|(name, control)| match control {
+                ButtonType::Knob(k) => Some((name.clone(), k.curr)),
+                _ => None,
+            })
+            .collect();
+        SessionState {
+            led: self.led,
+            led_hotcue: self.led_hotcue,
+            led_ext: self.led_ext,
+            led_bank80: self.led_bank80,
+            led_bank81: self.led_bank81,
+            layers: self.layers.active().clone(),
+            knobs,
+        }
+    }
+
+    /// Take over the state of a previous handler for the same controller. Call before [`Self::read`]:
+    /// the LEDs are written when reading starts, and knobs that moved while the device was away
+    /// are sent with the first report.
+    pub fn restore(&mut self, state: SessionState) {
+        self.led = state.led;
+        self.led_hotcue = state.led_hotcue;
+        self.led_ext = state.led_ext;
+        self.led_bank80 = state.led_bank80;
+        self.led_bank81 = state.led_bank81;
+        self.layers.restore(state.layers);
+        self.restored_knobs = state.knobs;
+    }
+
+    fn led_lookup(board: &X1mk1Board, layers: &Layers) -> LedLookup {
+        let mut led_map = std::collections::HashMap::new();
+        let mut rgb_leds = HashSet::new();
+        let mut modifier_leds = HashSet::new();
+        for button_type in board.buttons.values() {
+            match button_type {
+                ButtonType::Toggle(b) | ButtonType::Hold(b) | ButtonType::Hotcue(b) => {
+                    led_map.insert(b.midi_ctrl_ch, b.write_idx);
+                    if b.rgb_led {
+                        rgb_leds.insert(b.write_idx);
+                    }
+                }
+                _ => {}
+            }
+        }
+        for modifier in layers.modifiers() {
+            if modifier.mode != YamlModifierMode::Latching {
+                continue;
+            }
+            if let Some(ButtonType::Hold(b) | ButtonType::Toggle(b) | ButtonType::Hotcue(b)) = board.buttons.get(&modifier.button) {
+                modifier_leds.insert(b.write_idx);
+            }
+        }
+        (led_map, rgb_leds, modifier_leds)
+    }
+
+    /// Switch mappings when the device's profile changes or its file is edited.
+    pub fn follow_config(&mut self, follower: ConfigFollower) {
+        self.config = Some(follower);
+    }
+
+    fn check_config(&mut self) {
+        let Some(follower) = &mut self.config else { return };
+        let combo_held = self.board.profile_combo_held();
+        if combo_held && !self.profile_combo_held {
+            follower.cycle();
+        }
+        self.profile_combo_held = combo_held;
+        let reloaded = follower.poll();
+        let device = follower.poll_device();
+        if let Some(yaml_config) = reloaded {
+            self.apply_config(&yaml_config);
+            println!("✓ {} switched mapping", self.serial_number);
+        }
+        if let Some(device) = device {
+            self.use_device_config(&device);
         }
     }
 
-    fn fx_alt_cc_for_knob(mask: u8, base_cc: u8) -> Option<u8> {
-        match base_cc {
-            0 if (mask & FX_HOLD_MASK_FX1_PLAY) != 0 => Some(0),
-            2 if (mask & FX_HOLD_MASK_FX1_1) != 0 => Some(2),
-            4 if (mask & FX_HOLD_MASK_FX1_2) != 0 => Some(4),
-            6 if (mask & FX_HOLD_MASK_FX1_3) != 0 => Some(6),
-            1 if (mask & FX_HOLD_MASK_FX2_PLAY) != 0 => Some(1),
-            3 if (mask & FX_HOLD_MASK_FX2_1) != 0 => Some(3),
-            5 if (mask & FX_HOLD_MASK_FX2_2) != 0 => Some(5),
-            7 if (mask & FX_HOLD_MASK_FX2_3) != 0 => Some(7),
-            _ => None,
+    /// Swap in a new mapping mid-session. Controls that kept their read position keep their state;
+    /// the others start from the last report, so nothing is sent until they actually move.
+    pub(crate) fn apply_config(&mut self, yaml_config: &YamlConfig) {
+        let mut board = X1mk1Board::from_yaml(yaml_config);
+        let fresh = board.carry_state_from(&self.board);
+        if self.initialized {
+            for name in fresh {
+                if let Some(control) = board.buttons.get_mut(&name) {
+                    Self::set_baseline(control, &self.prev_buf);
+                }
+            }
         }
+        self.layers.replace_modifiers(yaml_config);
+        (self.led_map, self.rgb_leds, self.modifier_leds) = Self::led_lookup(&board, &self.layers);
+        self.board = board;
     }
 
-    fn use_shift_for_cc(cc: u8) -> bool {
-        !matches!(cc, 0..=7 | 8..=15 | 20..=23)
+    /// Take the control's state from `buf` without treating it as a change.
+    fn set_baseline(control: &mut ButtonType, buf: &[u8; 64]) {
+        match control {
+            ButtonType::Toggle(button) | ButtonType::Hold(button) | ButtonType::Hotcue(button) => {
+                // Bytes < 8 are noisy with knob/encoder data in HID mode
+                if button.read_i < 8 {
+                    return;
+                }
+                button.curr = button_state(buf, ReportFormat::Hid, button.read_i, button.read_j);
+                button.prev = button.curr;
+            }
+            ButtonType::Knob(k) => {
+                let position = k.reset(buf);
+                k.curr = k.response.value(position);
+                k.prev = k.curr;
+                k.fine = k.response.value14(position);
+            }
+            ButtonType::Encoder(e) => {
+                e.curr = encoder_position(buf, e.read_i, e.read_pos);
+                e.prev = e.curr;
+            }
+        }
     }
 
     fn set_led_idx(&mut self, idx: u8, val: u8) {
@@ -205,29 +307,47 @@ impl X1mk1Hid {
         }
     }
 
-    fn long_press_cc(&self, short_cc: u8) -> u8 {
-        short_cc.saturating_add(LONG_PRESS_OFFSET)
+    /// Apply the controller's `devices:` entry; fields it leaves out go back to their defaults.
+    /// The channel offset only works with the handler's own ports.
+    pub fn use_device_config(&mut self, device: &YamlDevice) {
+        self.led_brightness = device.led_brightness.unwrap_or(LED_BRIGHT).min(LED_BRIGHT);
+        if self.merged_input.is_none() {
+            self.midi_conn_out.offset = device.channel_offset.unwrap_or(0);
+        }
     }
 
-    fn send_press_cc(&mut self, status: u8, cc: u8) {
-        let _ = self.midi_conn_out.send(&[status, cc, 127]);
-        let _ = self.midi_conn_out.send(&[status, cc, 0]);
+    /// Scale LED values by the configured brightness.
+    fn dim(&self, frame: &mut [u8]) {
+        if self.led_brightness == LED_BRIGHT {
+            return;
+        }
+        for v in frame {
+            *v = (*v as u16 * self.led_brightness as u16 / LED_BRIGHT as u16) as u8;
+        }
+    }
+
+    /// Take LED input from `slot` of the shared merged port instead of opening an own input port.
+    pub fn use_merged_input(&mut self, port: std::sync::Arc<MergedPort>, slot: u8) {
+        self.merged_input = Some((port, slot));
     }
 
     pub(crate) fn init(&mut self, sender: mpsc::Sender<Vec<u8>>) {
+        if let Some((port, slot)) = &self.merged_input {
+            port.route(*slot, sender);
+            return;
+        }
         println!("Initializing MIDI input port...");
-        const MIDI_PORT_NAME: &str = "EASY KONTROL X1";
         let midi_in = MidiInput::new("Easy KONTROL X1").unwrap();
         let midi_conn_in = midi_in.create_virtual(
-            MIDI_PORT_NAME,
+            &self.port_name,
             move |_stamp, message: &[u8], _| {
                 sender.send(message.to_vec()).unwrap();
             }, ()).unwrap();
-        println!("✓ Created virtual MIDI input port: {}", MIDI_PORT_NAME);
+        println!("✓ Created virtual MIDI input port: {}", self.port_name);
         self.midi_conn_in = Some(midi_conn_in);
     }
 
-    pub(crate) fn read(&mut self) -> rusb::Result<()> {
+    pub(crate) fn read(&mut self) -> Result<(), TransportError> {
         println!("Reading from device (HID)");
         eprintln!("[HID] report_len=64(+rid) knob_log=v2");
         let (midi_tx, midi_rx) = mpsc::channel::<Vec<u8>>();
@@ -282,18 +402,20 @@ impl X1mk1Hid {
         }
         self.update_leds();
         
-        let mut read_count = 0;
         loop {
             if !self.run_flag.load(Ordering::Relaxed) {
                 std::thread::sleep(Duration::from_millis(100));
                 continue;
             }
+            self.check_config();
             match midi_rx.try_recv() {
                 Ok(message) => {
+                    let Some(message) = shift_channel_down(&message, self.midi_conn_out.offset) else { continue };
                     eprintln!("[MIDI IN] {:02x?}", message);
                     if message.len() < 3 {
                         continue;
                     }
+                    self.pickup.echo(&message);
                     let status = message[0];
                     let mut i = message[1] as usize;
                     let val = message[2];
@@ -301,11 +423,9 @@ impl X1mk1Hid {
                     if let Some(m) = mapped {
                         i = m as usize;
                     }
-                    if let Some(shift_idx) = self.shift_led_idx {
-                        if i == shift_idx as usize {
-                            // Shift LED is controlled locally, ignore MIDI input.
-                            continue;
-                        }
+                    if self.modifier_leds.contains(&(i as u8)) {
+                        // Modifier LEDs are controlled locally, ignore MIDI input.
+                        continue;
                     }
                     let max_idx = if self.led_bank == 0x80 {
                         51
@@ -327,7 +447,7 @@ impl X1mk1Hid {
                             self.set_led_idx(i as u8, v);
                         }
                         0xB0 => {
-                            if self.hotcue_leds.contains(&(i as u8)) {
+                            if self.rgb_leds.contains(&(i as u8)) {
                                 let (r, g, b) = Self::hotcue_rgb_from_val(val);
                                 let base = i as i32;
                                 let red = base - 2;
@@ -366,37 +486,17 @@ impl X1mk1Hid {
                 Err(_) => {}
             }
             
-            match self.handle.read_timeout(&mut self.usb_buffer, self.usb_timeout) {
+            match self.transport.read_report(&mut self.usb_buffer, self.usb_timeout) {
                 Ok(len) => {
                     if len > 0 {
-                        // Zero any unread tail so stale bytes don't linger
-                        for i in len..self.usb_buffer.len() {
-                            self.usb_buffer[i] = 0;
-                        }
-                        read_count += 1;
-                        let mut buf64 = [0u8; 64];
-                        if len >= 65 {
-                            // Treat first byte as report ID
-                            let report_id = self.usb_buffer[0];
-                            if report_id != 0 {
-                                eprintln!("[HID] report_id=0x{:02x}", report_id);
-                            }
-                            buf64.copy_from_slice(&self.usb_buffer[1..65]);
-                        } else {
-                            // No report ID; take first 64 bytes (or whatever was read)
-                            let copy_len = len.min(64);
-                            buf64[..copy_len].copy_from_slice(&self.usb_buffer[..copy_len]);
-                        }
-                        self.read_state(buf64);
+                        let report = self.usb_buffer;
+                        self.process_report(&report[..len], Instant::now());
                     }
                 }
+                Err(TransportError::Timeout) => self.tick_gestures(Instant::now()),
                 Err(e) => {
-                    let error_msg = e.to_string();
-                    // Only report non-timeout errors
-                    if !error_msg.contains("timeout") && !error_msg.contains("Timeout") {
-                        eprintln!("HID read error: {}", e);
-                        return Err(rusb::Error::Io);
-                    }
+                    eprintln!("HID read error: {}", e);
+                    return Err(e);
                 }
             }
             if self.led_test {
@@ -411,54 +511,42 @@ impl X1mk1Hid {
         }
     }
 
-    fn read_state(&mut self, buf: [u8; 64]) {
+    /// Handle one raw report as read from the transport (with or without the leading report ID).
+    pub(crate) fn process_report(&mut self, report: &[u8], now: Instant) {
+        if report.len() >= 65 && report[0] != 0 {
+            eprintln!("[HID] report_id=0x{:02x}", report[0]);
+        }
+        let buf64 = hid_payload(report);
+        self.read_state(buf64, now);
+    }
+
+    fn read_state(&mut self, buf: [u8; 64], now: Instant) {
         // Initialize on first read
         if !self.initialized {
             self.initialized = true;
             
             // Initialize all control states without firing MIDI events
-            for (_ctrl_name, button_type) in &mut self.board.buttons {
-                match button_type {
-                    crate::x1_board::ButtonType::Toggle(ref mut button) => {
-                        // Bytes < 8 are noisy with knob/encoder data in HID mode
-                        if button.read_i < 8 { continue; }
-                        let byte = buf[button.read_i as usize];
-                        button.curr = ((byte >> button.read_j) & 1) != 0;
-                        button.prev = button.curr;
-                    },
-                    crate::x1_board::ButtonType::Hold(ref mut button) => {
-                        // Bytes < 8 are noisy with knob/encoder data in HID mode
-                        if button.read_i < 8 { continue; }
-                        let byte = buf[button.read_i as usize];
-                        button.curr = ((byte >> button.read_j) & 1) != 0;
-                        button.prev = button.curr;
-                    },
-                    crate::x1_board::ButtonType::Hotcue(ref mut button) => {
-                        // Bytes < 8 are noisy with knob/encoder data in HID mode
-                        if button.read_i < 8 { continue; }
-                        let byte = buf[button.read_i as usize];
-                        button.curr = ((byte >> button.read_j) & 1) != 0;
-                        button.prev = button.curr;
-                    },
-                    crate::x1_board::ButtonType::Knob(ref mut k) => {
-                        k.curr = knob_to_midi(buf[k.read_i as usize], buf[k.read_j as usize]);
-                        k.prev = k.curr;
-                    },
-                    crate::x1_board::ButtonType::Encoder(ref mut e) => {
-                        let mut binnum = [0; 8];
-                        hex2bin(buf[e.read_i as usize], &mut binnum);
-                        e.curr = match e.read_pos {
-                            's' => binnum[0] + binnum[1] * 2 + binnum[2] * 4 + binnum[3] * 8,
-                            'n' => binnum[4] + binnum[5] * 2 + binnum[6] * 4 + binnum[7] * 8,
-                            _ => 0,
-                        };
-                        e.prev = e.curr;
-                    },
+            for (ctrl_name, button_type) in &mut self.board.buttons {
+                Self::set_baseline(button_type, &buf);
+                if let (ButtonType::Knob(k), Some(&value)) = (button_type, self.restored_knobs.get(ctrl_name)) {
+                    k.curr = value;
+                    k.prev = value;
+                    // Only the 7-bit value is kept; a 14-bit knob resends its exact position.
+                    k.fine = u16::MAX;
                 }
             }
+            // A momentary modifier held when the device went away was released since; drop its layer.
+            let buttons = &self.board.buttons;
+            self.layers.release_unheld(|name| {
+                matches!(buttons.get(name), Some(ButtonType::Toggle(b) | ButtonType::Hold(b) | ButtonType::Hotcue(b)) if b.curr)
+            });
             eprintln!("[INIT] Initialization complete");
             self.prev_buf = buf;
-            return; // Skip button processing this read
+            if self.restored_knobs.is_empty() {
+                return; // Skip button processing this read
+            }
+            // Reconnect: the pass below sends every knob that differs from its restored value.
+            self.restored_knobs.clear();
         }
 
         // Debug: detect byte changes to discover button layout
@@ -488,18 +576,12 @@ impl X1mk1Hid {
         }
 
         let mut pending_led: Option<(u8, u8)> = None;
-        let mut pending_cc: Vec<(u8, u8)> = Vec::new();
+        let mut pending: Vec<Vec<u8>> = Vec::new();
+        let hotcue = self.layers.is_active(HOTCUE_LAYER);
         for (ctrl_name, button_type) in &mut self.board.buttons {
-            let is_play = matches!(ctrl_name.as_str(), "DECK_A_BUTTON_PLAY" | "DECK_B_BUTTON_PLAY");
-            let is_cue = matches!(ctrl_name.as_str(), "DECK_A_BUTTON_CUE" | "DECK_B_BUTTON_CUE");
-            let is_play_or_cue = is_play || is_cue;
-            let is_shift = ctrl_name.as_str() == "SHIFT";
-            let is_hotcue_button =
-                ctrl_name.as_str().starts_with("DECK_A_HOTCUE")
-                    || ctrl_name.as_str().starts_with("DECK_B_HOTCUE");
             match button_type {
-                ButtonType::Toggle(ref mut button) => {
-                    if self.hotcue && button.hotcue_ignore {
+                ButtonType::Toggle(ref mut button) | ButtonType::Hold(ref mut button) => {
+                    if hotcue && button.hotcue_ignore {
                         continue;
                     }
                     // Bytes < 8 are noisy with knob/encoder data in HID mode
@@ -509,8 +591,7 @@ impl X1mk1Hid {
                     if button.read_i == 23 && self.encoder_quiet_count < 3 {
                         continue;
                     }
-                    let byte = buf[button.read_i as usize];
-                    let new_state = ((byte >> button.read_j) & 1) != 0;
+                    let new_state = button_state(&buf, ReportFormat::Hid, button.read_i, button.read_j);
                     
                     // Per-button debouncing
                     if new_state != button.curr {
@@ -527,152 +608,42 @@ impl X1mk1Hid {
                     if button.curr != button.prev && button.debounce_count >= debounce_needed {
                         if button.curr {
                             eprintln!("[BUTTON] {} pressed", ctrl_name);
-                            button.press_time = Some(Instant::now());
-                            if ctrl_name.eq("HOTCUE") {
-                                self.hotcue = !self.hotcue;
-                                let val = if self.hotcue { LED_BRIGHT } else { LED_DIM };
-                                pending_led = Some((button.write_idx, val));
-                            }
-                            if let Some(bit) = Self::fx_hold_bit(button.midi_ctrl_ch) {
-                                self.fx_hold_mask |= bit;
+                            self.layers.press(ctrl_name);
+                            if let Some(gestures) = button.recognizer(&self.layers, true) {
+                                let fired = gestures.press(now);
+                                pending.extend(button.gesture_messages(&self.layers, OUT_CHANNEL, &fired));
+                            } else if button.momentary {
+                                let channel = button.message.channel.unwrap_or(OUT_CHANNEL);
+                                let (channel, number) = self.layers.output(&button.layers, channel, button.midi_ctrl_ch);
+                                self.midi_conn_out.send_all(button.message.button(channel, number, true));
                             }
                             button_event_bytes[button.read_i as usize] = true;
                         } else {
-                            if let Some(bit) = Self::fx_hold_bit(button.midi_ctrl_ch) {
-                                self.fx_hold_mask &= !bit;
-                            }
-                            let elapsed = button
-                                .press_time
-                                .take()
-                                .map(|t| t.elapsed().as_millis() as u64)
-                                .unwrap_or(0);
-                            let is_long = self.shift == 1
-                                && !is_play_or_cue
-                                && !is_shift
-                                && Self::fx_hold_bit(button.midi_ctrl_ch).is_none()
-                                && elapsed >= LONG_PRESS_MS;
-                            let cc = if is_long {
-                                button.midi_ctrl_ch.saturating_add(LONG_PRESS_OFFSET)
-                            } else {
-                                button.midi_ctrl_ch
-                            };
-                            let kind = if is_long { "long" } else { "short" };
-                            eprintln!("[BUTTON] {} {} -> CC {}", ctrl_name, kind, cc);
-                            let status = if is_play || is_shift {
-                                MIDI_CHANNEL
-                            } else if Self::use_shift_for_cc(cc) {
-                                MIDI_CHANNEL + self.shift
-                            } else {
-                                MIDI_CHANNEL
-                            };
-                            if !is_shift {
-                                pending_cc.push((status, cc));
-                            }
-                            button_event_bytes[button.read_i as usize] = true;
-                        }
-                        button.prev = button.curr;
-                        button.debounce_count = 0;  // Reset after trigger
-                    }
-                }
-                ButtonType::Hold(ref mut button) => {
-                    if self.hotcue && button.hotcue_ignore {
-                        continue;
-                    }
-                    // Bytes < 8 are noisy with knob/encoder data in HID mode
-                    if button.read_i < 8 {
-                        continue;
-                    }
-                    if button.read_i == 23 && self.encoder_quiet_count < 3 {
-                        continue;
-                    }
-                    let byte = buf[button.read_i as usize];
-                    let new_state = ((byte >> button.read_j) & 1) != 0;
-                    
-                    // Per-button debouncing
-                    if new_state != button.curr {
-                        // Button state changed, reset debounce counter
-                        button.curr = new_state;
-                        button.debounce_count = 1;
-                    } else if button.debounce_count > 0 && button.debounce_count < 3 {
-                        // State is consistent, increment counter
-                        button.debounce_count += 1;
-                    }
-                    
-                    let debounce_needed = if button.read_i >= 19 { 1 } else { 3 };
-                    // Only trigger MIDI on state change if debounced
-                    if button.curr != button.prev && button.debounce_count >= debounce_needed {
-                        if button.curr {
-                            eprintln!("[BUTTON] {} pressed", ctrl_name);
-                            button.press_time = Some(Instant::now());
-                            if is_shift {
-                                // Latching shift: toggle on release.
-                            }
-                            if let Some(bit) = Self::fx_hold_bit(button.midi_ctrl_ch) {
-                                self.fx_hold_mask |= bit;
-                            }
-                            if is_play_or_cue || is_hotcue_button {
-                                let status = if is_play {
-                                    MIDI_CHANNEL
-                                } else {
-                                    MIDI_CHANNEL + self.shift
-                                };
-                                let _ = self.midi_conn_out.send(&[status, button.midi_ctrl_ch, 127]);
-                            }
-                            button_event_bytes[button.read_i as usize] = true;
-                        } else {
-                            if let Some(bit) = Self::fx_hold_bit(button.midi_ctrl_ch) {
-                                self.fx_hold_mask &= !bit;
-                            }
-                            let elapsed = button
-                                .press_time
-                                .take()
-                                .map(|t| t.elapsed().as_millis() as u64)
-                                .unwrap_or(0);
-                            let is_fx_button = Self::fx_hold_bit(button.midi_ctrl_ch).is_some();
-                            if is_play_or_cue || is_hotcue_button {
-                                let status = if is_play {
-                                    MIDI_CHANNEL
-                                } else {
-                                    MIDI_CHANNEL + self.shift
-                                };
-                                let _ = self.midi_conn_out.send(&[status, button.midi_ctrl_ch, 0]);
-                                button.prev = button.curr;
-                                button.debounce_count = 0;
+                            let modifier = self.layers.release(ctrl_name);
+                            button.prev = button.curr;
+                            button.debounce_count = 0;
+                            if let Some((YamlModifierMode::Latching, on)) = modifier {
+                                // Latching modifiers only switch their layer.
+                                let val = if on { LED_BRIGHT } else { LED_DIM };
+                                pending_led = Some((button.write_idx, val));
+                                button_event_bytes[button.read_i as usize] = true;
                                 continue;
                             }
-                            let is_long = self.shift == 1
-                                && !is_play_or_cue
-                                && !is_shift
-                                && !is_fx_button
-                                && elapsed >= LONG_PRESS_MS;
-                            if is_fx_button && elapsed >= LONG_PRESS_MS {
-                                // FX buttons: long hold only affects knob channel, no button action on release.
-                                button.prev = button.curr;
-                                button.debounce_count = 0;
+                            if let Some(gestures) = button.recognizer(&self.layers, false) {
+                                let fired = gestures.release(now);
+                                pending.extend(button.gesture_messages(&self.layers, OUT_CHANNEL, &fired));
+                                button_event_bytes[button.read_i as usize] = true;
                                 continue;
                             }
-                            let cc = if is_long {
-                                button.midi_ctrl_ch.saturating_add(LONG_PRESS_OFFSET)
-                            } else {
-                                button.midi_ctrl_ch
-                            };
-                            let kind = if is_long { "long" } else { "short" };
-                            eprintln!("[BUTTON] {} {} -> CC {}", ctrl_name, kind, cc);
-                            if is_shift {
-                                self.shift = if self.shift == 0 { 1 } else { 0 };
-                                let val = if self.shift == 1 { LED_BRIGHT } else { LED_DIM };
-                                pending_led = Some((button.write_idx, val));
-                            }
-                            let status = if is_play || is_shift {
-                                MIDI_CHANNEL
-                            } else if Self::use_shift_for_cc(cc) {
-                                MIDI_CHANNEL + self.shift
-                            } else {
-                                MIDI_CHANNEL
-                            };
-                            if !is_shift {
-                                pending_cc.push((status, cc));
+                            let channel = button.message.channel.unwrap_or(OUT_CHANNEL);
+                            let (channel, number) = self.layers.output(&button.layers, channel, button.midi_ctrl_ch);
+                            if button.momentary {
+                                self.midi_conn_out.send_all(button.message.button(channel, number, false));
+                                continue;
                             }
+                            eprintln!("[BUTTON] {} -> {:?} {}", ctrl_name, button.message.kind, number);
+                            pending.extend(button.message.button(channel, number, true));
+                            pending.extend(button.message.button(channel, number, false));
                             button_event_bytes[button.read_i as usize] = true;
                         }
                         button.prev = button.curr;
@@ -680,43 +651,36 @@ impl X1mk1Hid {
                     }
                 }
                 ButtonType::Hotcue(ref mut button) => {
-                    if !self.hotcue {
+                    if !hotcue {
                         continue;
                     }
-                    let byte = buf[button.read_i as usize];
                     if button.read_i < 8 {
                         continue;
                     }
                     if button.read_i == 23 && self.encoder_quiet_count < 3 {
                         continue;
                     }
-                    button.curr = ((byte >> button.read_j) & 1) != 0;
+                    button.curr = button_state(&buf, ReportFormat::Hid, button.read_i, button.read_j);
                     if button.curr == button.prev {
                         continue;
                     } else if button.curr {
-                        button.press_time = Some(Instant::now());
+                        if let Some(gestures) = button.recognizer(&self.layers, true) {
+                            let fired = gestures.press(now);
+                            pending.extend(button.gesture_messages(&self.layers, OUT_CHANNEL, &fired));
+                        }
+                        button_event_bytes[button.read_i as usize] = true;
+                        button.prev = button.curr;
+                    } else if let Some(gestures) = button.recognizer(&self.layers, false) {
+                        let fired = gestures.release(now);
+                        pending.extend(button.gesture_messages(&self.layers, OUT_CHANNEL, &fired));
                         button_event_bytes[button.read_i as usize] = true;
                         button.prev = button.curr;
                     } else {
-                        let elapsed = button
-                            .press_time
-                            .take()
-                            .map(|t| t.elapsed().as_millis() as u64)
-                            .unwrap_or(0);
-                        let is_long = self.shift == 1
-                            && !is_play_or_cue
-                            && !is_shift
-                            && Self::fx_hold_bit(button.midi_ctrl_ch).is_none()
-                            && elapsed >= LONG_PRESS_MS;
-                        let cc = if is_long {
-                            button.midi_ctrl_ch.saturating_add(LONG_PRESS_OFFSET)
-                        } else {
-                            button.midi_ctrl_ch
-                        };
-                        let kind = if is_long { "long" } else { "short" };
-                        eprintln!("[BUTTON] {} {} -> CC {}", ctrl_name, kind, cc);
-                        let status = MIDI_CHANNEL + self.shift;
-                        pending_cc.push((status, cc));
+                        let channel = button.message.channel.unwrap_or(OUT_CHANNEL);
+                        let (channel, number) = self.layers.output(&button.layers, channel, button.midi_ctrl_ch);
+                        eprintln!("[BUTTON] {} -> {:?} {}", ctrl_name, button.message.kind, number);
+                        pending.extend(button.message.button(channel, number, true));
+                        pending.extend(button.message.button(channel, number, false));
                         button_event_bytes[button.read_i as usize] = true;
                         button.prev = button.curr;
                     }
@@ -727,14 +691,14 @@ impl X1mk1Hid {
             }
         }
 
-        let fx_hold_mask = self.fx_hold_mask;
-        let shift_active = self.shift;
+        let mut pickup_leds: Vec<(u8, u8)> = Vec::new();
         for (_ctrl_name, button_type) in &mut self.board.buttons {
             match button_type {
                 ButtonType::Knob(ref mut k) => {
                     let raw_i = buf[k.read_i as usize];
                     let raw_j = buf[k.read_j as usize];
-                    let new_val = knob_to_midi(raw_i, raw_j);
+                    let position = k.position(&buf);
+                    let new_val = k.response.value(position);
                     if new_val != k.curr {
                         eprintln!(
                             "[KNOB] read_i={} read_j={} raw=0x{:02x}/0x{:02x}: {} -> {}",
@@ -747,48 +711,37 @@ impl X1mk1Hid {
                         );
                     }
                     k.curr = new_val;
-                    if k.curr != k.prev {
-                        // Skip MIDI if this knob shares a byte with a button event this frame
-                        if button_event_bytes[k.read_i as usize] || button_event_bytes[k.read_j as usize] {
-                            k.prev = k.curr;
-                            continue;
-                        }
-                        let cc = k.midi_ctrl_ch;
-                        let status = if Self::fx_alt_cc_for_knob(fx_hold_mask, cc).is_some() {
-                            MIDI_CHANNEL_FX_HOLD
-                        } else if Self::use_shift_for_cc(cc) {
-                            MIDI_CHANNEL + shift_active
+                    let fine = k.response.value14(position);
+                    let moved = if k.message.high_res { fine != k.fine } else { k.curr != k.prev };
+                    // Skip MIDI if this knob shares a byte with a button event this frame; a knob with
+                    // its own filter leaves that to the filter.
+                    let masked = !k.filter.is_active()
+                        && (button_event_bytes[k.read_i as usize] || button_event_bytes[k.read_j as usize]);
+                    let channel = k.message.channel.unwrap_or(OUT_CHANNEL);
+                    let (channel, number) = self.layers.output(&k.layers, channel, k.midi_ctrl_ch);
+                    if moved && !masked {
+                        if k.pickup && !self.pickup.take(channel, number, k.prev, k.curr, k.pickup_wait) {
+                            // Not picked up yet; the target keeps its value.
+                        } else if k.message.high_res {
+                            self.midi_conn_out.send_all(k.message.value14(channel, number, fine));
                         } else {
-                            MIDI_CHANNEL
-                        };
-                        let _ = self.midi_conn_out.send(&[status, cc, k.curr]);
+                            self.midi_conn_out.send_all(k.message.value(channel, number, k.curr));
+                        }
+                    }
+                    if let Some(idx) = k.pickup_led {
+                        let waiting = self.pickup.waiting(channel, number, k.curr, k.pickup_wait);
+                        pickup_leds.push((idx, if waiting { LED_BRIGHT } else { LED_DIM }));
                     }
                     k.prev = k.curr;
+                    k.fine = fine;
                 }
                 ButtonType::Encoder(ref mut encoder) => {
-                    let mut binnum = [0; 8];
-                    hex2bin(buf[encoder.read_i as usize], &mut binnum);
-                    match encoder.read_pos {
-                        's' => {
-                            encoder.curr = binnum[0] + binnum[1] * 2 + binnum[2] * 4 + binnum[3] * 8;
-                        }
-                        'e' => {
-                            encoder.curr = binnum[4] + binnum[5] * 2 + binnum[6] * 4 + binnum[7] * 8;
-                        }
-                        _ => panic!("Invalid read_pos"),
-                    }
+                    encoder.curr = encoder_position(&buf, encoder.read_i, encoder.read_pos);
                     if encoder.curr != encoder.prev {
-                        // Clockwise init
-                        let mut velocity = 1;
-                        if (encoder.prev == 15 && encoder.curr == 0) || (encoder.prev == 0 && encoder.curr == 15) {
-                            // Full rotation special case
-                            velocity = if encoder.prev == 15 { 1 } else { 127 };
-                        } else if encoder.curr > encoder.prev {
-                            velocity = 1;
-                        } else {
-                            velocity = 127;
-                        }
-                        let _ = self.midi_conn_out.send(&[MIDI_CHANNEL + shift_active, encoder.midi_ctrl_ch, velocity]);
+                        let delta = encoder_delta(encoder.prev, encoder.curr);
+                        let channel = encoder.message.channel.unwrap_or(OUT_CHANNEL);
+                        let (channel, number) = self.layers.output(&encoder.layers, channel, encoder.midi_ctrl_ch);
+                        self.midi_conn_out.send_all(encoder.output.turn(&encoder.message, channel, number, delta, now));
                     }
                     encoder.prev = encoder.curr;
                 }
@@ -798,19 +751,40 @@ impl X1mk1Hid {
         if let Some((idx, val)) = pending_led {
             self.set_led_idx(idx, val);
         }
-        for (status, cc) in pending_cc {
-            self.send_press_cc(status, cc);
+        for (idx, val) in pickup_leds {
+            self.set_led_idx(idx, val);
         }
+        self.midi_conn_out.send_all(pending);
         self.prev_buf = buf;
+        self.tick_gestures(now);
+    }
+
+    /// Send what button gestures decided by time alone: long presses reached while held, repeats,
+    /// and taps whose double-tap window ran out.
+    fn tick_gestures(&mut self, now: Instant) {
+        let hotcue = self.layers.is_active(HOTCUE_LAYER);
+        let mut messages = Vec::new();
+        for button_type in self.board.buttons.values_mut() {
+            let button = match button_type {
+                ButtonType::Toggle(b) | ButtonType::Hold(b) if !(hotcue && b.hotcue_ignore) => b,
+                ButtonType::Hotcue(b) if hotcue => b,
+                _ => continue,
+            };
+            let Some(gestures) = &mut button.gestures else { continue };
+            let fired = gestures.tick(now);
+            messages.extend(button.gesture_messages(&self.layers, OUT_CHANNEL, &fired));
+        }
+        self.midi_conn_out.send_all(messages);
     }
 
     fn update_leds(&mut self) {
         let mut led = self.led;
-        if self.hotcue {
+        if self.layers.is_active(HOTCUE_LAYER) {
             for i in 9..25 {
                 led[i] = self.led_hotcue[i - 9];
             }
         }
+        self.dim(&mut led);
         if self.led_debug {
             let preview_len = 8.min(led.len());
             eprintln!("[LED STATE] {:?}", &led[..preview_len]);
@@ -864,6 +838,7 @@ impl X1mk1Hid {
             } else {
                 if self.led_bank == 0x80 {
                     buf80[1..].copy_from_slice(&self.led_bank80);
+                    self.dim(&mut buf80[1..]);
                 } else {
                     buf80[1..1 + led.len()].copy_from_slice(&led);
                 }
@@ -880,10 +855,12 @@ impl X1mk1Hid {
             } else {
                 if self.led_bank == 0x81 {
                     buf81[1..].copy_from_slice(&self.led_bank81);
+                    self.dim(&mut buf81[1..]);
                 } else {
                     buf81[1..1 + led.len()].copy_from_slice(&led);
                     if self.led_ext_enabled {
                         buf81[1 + led.len()..].copy_from_slice(&self.led_ext);
+                        self.dim(&mut buf81[1 + led.len()..]);
                     }
                 }
             }
@@ -892,7 +869,7 @@ impl X1mk1Hid {
 
         let mut ok = false;
         for (payload, label) in payloads.iter() {
-            match self.handle.write(payload) {
+            match self.transport.write_report(payload) {
                 Ok(n) => {
                     ok = true;
                     if self.led_debug {
@@ -923,7 +900,7 @@ impl X1mk1Hid {
             payloads.push((f1, "feat:f1_len17"));
         }
         for (payload, label) in payloads.iter() {
-            match self.handle.send_feature_report(payload) {
+            match self.transport.send_feature_report(payload) {
                 Ok(()) => {
                     ok = true;
                     if self.led_debug {
@@ -938,6 +915,8 @@ impl X1mk1Hid {
             }
         }
 
+        self.transport.flush();
+
         if !ok && self.led_report_id == 0 {
             self.led_report_id = 1;
         }
@@ -958,3 +937,212 @@ impl X1mk1Hid {
         }
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::path::Path;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::conf::{YamlDevice, YamlGestures, YamlMessageType};
+    use crate::devices::BASE_PORT_NAME;
+    use crate::midi_out::RecordingSink;
+    use crate::transport::MockTransport;
+    use crate::validate::load_config_file;
+
+    fn board_config() -> YamlConfig {
+        load_config_file(&Path::new(env!("CARGO_MANIFEST_DIR")).join("board.yml")).unwrap()
+    }
+
+    /// An HID handler on a mock device running `config`, and the sink that records what it sends.
+    pub(crate) fn handler(config: YamlConfig) -> (X1mk1Hid, RecordingSink) {
+        let sink = RecordingSink::new();
+        let x1 = X1mk1Hid::with_output(
+            Box::new(MockTransport::new()),
+            Box::new(sink.clone()),
+            "TEST".to_string(),
+            BASE_PORT_NAME.to_string(),
+            config,
+            &Settings::default(),
+            Arc::new(AtomicBool::new(true)),
+        );
+        (x1, sink)
+    }
+
+    /// Latching SHIFT lights its LED in the next frame written to the device.
+    #[test]
+    fn latched_modifier_led_is_written() {
+        let transport = MockTransport::new();
+        let writes = transport.writes();
+        let mut x1 = X1mk1Hid::with_output(
+            Box::new(transport),
+            Box::new(RecordingSink::new()),
+            "TEST".to_string(),
+            "TEST".to_string(),
+            board_config(),
+            &Settings::default(),
+            Arc::new(AtomicBool::new(true)),
+        );
+        let now = Instant::now();
+        let mut report = [0u8; 64];
+        x1.process_report(&report, now);
+        report[20] = 0b100; // SHIFT
+        x1.process_report(&report, now);
+        report[20] = 0;
+        x1.process_report(&report, now);
+        x1.update_leds();
+
+        let writes = writes.lock().unwrap();
+        let frame = writes.iter().rev().find(|w| w[0] == 0x80).expect("no 0x80 LED report written");
+        assert_eq!(frame.len(), 52);
+        assert_eq!(frame[1 + 17], LED_BRIGHT);
+        assert_eq!(frame[1 + 31], LED_DIM);
+    }
+
+    /// A reload that only changes CCs must not emit anything by itself, and later moves use the new CC.
+    #[test]
+    fn reload_keeps_control_state() {
+        let config = board_config();
+        let (mut x1, sink) = handler(config.clone());
+        let start = Instant::now();
+        let mut report = [0u8; 64];
+        report[2] = 0x08; // FX1_KNOB_DRY at mid scale
+        x1.process_report(&report, start);
+        assert!(sink.take().is_empty());
+
+        let mut reloaded = config;
+        for button in &mut reloaded.buttons {
+            if button.name == "FX1_KNOB_DRY" {
+                button.midi_ctrl_ch = 100;
+            }
+        }
+        x1.apply_config(&reloaded);
+        x1.process_report(&report, start);
+        assert!(sink.take().is_empty(), "reload emitted MIDI");
+
+        report[1] = 0xFF;
+        report[2] = 0x0F;
+        x1.process_report(&report, start);
+        assert_eq!(sink.take(), vec![vec![0xB0, 100, 127]]);
+    }
+
+    /// A device `channel_offset` moves every message the handler sends up by that many channels,
+    /// until a reloaded `devices:` entry says otherwise.
+    #[test]
+    fn device_channel_offset() {
+        let (mut x1, sink) = handler(board_config());
+        x1.use_device_config(&YamlDevice {
+            serial: "TEST".to_string(),
+            channel_offset: Some(4),
+            ..Default::default()
+        });
+        let start = Instant::now();
+        let mut report = [0u8; 64];
+        x1.process_report(&report, start);
+        report[1] = 0xFF;
+        report[2] = 0x0F; // FX1_KNOB_DRY to full scale
+        x1.process_report(&report, start);
+        assert_eq!(sink.take(), vec![vec![0xB4, 0, 127]]);
+
+        // A reload that drops the entry goes back to channel 1.
+        x1.use_device_config(&YamlDevice::default());
+        report[1] = 0;
+        report[2] = 0;
+        x1.process_report(&report, start);
+        assert_eq!(sink.take(), vec![vec![0xB0, 0, 0]]);
+    }
+
+    /// A `high_res` knob sends every step of its 12-bit reading, not just the 7-bit ones.
+    #[test]
+    fn high_res_knob() {
+        let mut config = board_config();
+        for button in &mut config.buttons {
+            if button.name == "FX1_KNOB_DRY" {
+                button.message = Some(YamlMessageType::PitchBend);
+                button.high_res = Some(true);
+            }
+        }
+        let (mut x1, sink) = handler(config);
+        let start = Instant::now();
+        let mut report = [0u8; 64];
+        x1.process_report(&report, start);
+        report[1] = 0x01; // one 12-bit step, still 0 at 7 bits
+        x1.process_report(&report, start);
+        assert_eq!(sink.take(), vec![vec![0xE0, 4, 0]]);
+        report[1] = 0xFF;
+        report[2] = 0x0F;
+        x1.process_report(&report, start);
+        assert_eq!(sink.take(), vec![vec![0xE0, 0x7F, 0x7F]]);
+    }
+
+    /// A button's long press fires once the hold time passes, while the button is still down.
+    #[test]
+    fn long_press_gesture() {
+        let mut config = board_config();
+        for button in &mut config.buttons {
+            if button.name == "DECK_A_BUTTON_SYNC" {
+                button.gestures = Some(YamlGestures {
+                    long_press_ms: Some(600),
+                    ..YamlGestures::default()
+                });
+            }
+        }
+        let (mut x1, sink) = handler(config);
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut report = [0u8; 64];
+        x1.process_report(&report, start);
+        report[22] = 0b100; // DECK_A_BUTTON_SYNC down
+        x1.process_report(&report, at(10));
+        report[22] = 0;
+        x1.process_report(&report, at(100));
+        assert_eq!(sink.take(), vec![vec![0xB0, 42, 127], vec![0xB0, 42, 0]]);
+
+        report[22] = 0b100;
+        x1.process_report(&report, at(200));
+        x1.process_report(&report, at(850));
+        assert_eq!(sink.take(), vec![vec![0xB0, 74, 127], vec![0xB0, 74, 0]]);
+        report[22] = 0;
+        x1.process_report(&report, at(900));
+        assert!(sink.take().is_empty());
+    }
+
+    /// With `pickup_wait`, holding FX1 PLAY moves FX1 DRY to a target it knows nothing about; it
+    /// stays quiet, with its LED lit, until the software echoes a value and the knob crosses it.
+    #[test]
+    fn pickup_waits_across_a_layer_switch() {
+        let mut config = board_config();
+        for button in &mut config.buttons {
+            if button.name == "FX1_KNOB_DRY" {
+                button.pickup = Some(true);
+                button.pickup_wait = Some(true);
+                button.pickup_led = Some(3);
+            }
+        }
+        let (mut x1, sink) = handler(config);
+        let now = Instant::now();
+        let mut report = [0u8; 64];
+        let knob = |report: &mut [u8; 64], raw: u16| {
+            report[1] = raw as u8;
+            report[2] = (raw >> 8) as u8;
+        };
+        x1.process_report(&report, now);
+        x1.pickup.echo(&[0xB0, 0, 0]);
+        knob(&mut report, 0x400);
+        x1.process_report(&report, now);
+        assert_eq!(sink.take().len(), 1);
+
+        report[19] = 0x80; // hold FX1_BUTTON_PLAY
+        x1.process_report(&report, now);
+        knob(&mut report, 0xC00);
+        x1.process_report(&report, now);
+        assert!(sink.take().is_empty());
+        assert_eq!(x1.led_bank80[3], LED_BRIGHT);
+
+        x1.pickup.echo(&[0xB2, 0, 110]);
+        knob(&mut report, 0xFFF);
+        x1.process_report(&report, now);
+        assert_eq!(sink.take(), vec![vec![0xB2, 0, 127]]);
+        assert_eq!(x1.led_bank80[3], LED_DIM);
+    }
+}